@@ -1,12 +1,17 @@
 use iced::advanced::graphics::core::Element;
 use iced::widget::container;
 use iced::{
-    executor, window, Application, Command, Length, Settings, Subscription,
-    Theme,
+    executor, window, Application, Command, Font, Length, Settings,
+    Subscription, Theme,
+};
+use iced_term::{
+    self, BackendSettings, ClipboardFormatter, FontSettings, Osc52, Term,
+    TermSettings,
 };
-use iced_term::{self, BackendSettings, FontSettings, Term, TermSettings};
 use std::collections::HashMap;
 
+const DEFAULT_TITLE: &str = "Terminal app";
+
 fn main() -> iced::Result {
     App::run(Settings {
         antialiasing: true,
@@ -21,9 +26,11 @@ fn main() -> iced::Result {
 #[derive(Debug, Clone)]
 pub enum Message {
     TermEvent(iced_term::Event),
+    ClipboardRead(u64, ClipboardFormatter, Option<String>),
 }
 
 struct App {
+    title: String,
     tabs: HashMap<u64, Term>,
 }
 
@@ -34,62 +41,78 @@ impl Application for App {
     type Flags = ();
 
     fn new(_flags: ()) -> (Self, Command<Message>) {
-        let system_shell = env!("SHELL");
         let tab_id = 0;
         let tab = iced_term::Term::new(
             tab_id,
             TermSettings {
-                font: FontSettings { size: 14.0 },
-                backend: BackendSettings {
-                    shell: system_shell.to_string(),
-                    ..BackendSettings::default()
+                font: FontSettings {
+                    size: 14.0,
+                    font_type: Font::MONOSPACE,
+                    ..FontSettings::default()
                 },
+                backend: BackendSettings::builder()
+                    .osc52(Osc52::CopyPaste)
+                    .build(),
+                ..TermSettings::default()
             },
         );
         let mut tabs = HashMap::new();
         tabs.insert(tab_id, tab);
-        (Self { tabs }, Command::none())
+        (
+            Self {
+                title: String::from(DEFAULT_TITLE),
+                tabs,
+            },
+            Command::none(),
+        )
     }
 
     fn title(&self) -> String {
-        String::from("Terminal app")
+        self.title.clone()
     }
 
     fn update(&mut self, message: Self::Message) -> iced::Command<Message> {
         match message {
             Message::TermEvent(event) => {
                 match event {
-                    iced_term::Event::InputReceived(id, c) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::WriteToPTY(c))
+                    iced_term::Event::TitleChanged(_, title) => {
+                        self.title = title;
+                    },
+                    iced_term::Event::TitleReset(_) => {
+                        self.title = String::from(DEFAULT_TITLE);
                     },
-                    iced_term::Event::DataUpdated(id, data) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::RenderData(data))
+                    iced_term::Event::ProcessExited(_, _) => {
+                        return window::close();
                     },
-                    iced_term::Event::ContainerScrolled(id, delta) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::Scroll(delta as i32))
+                    iced_term::Event::ClipboardStoreRequested(_, text) => {
+                        return iced::clipboard::write(text);
                     },
-                    iced_term::Event::Resized(id, size) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::Resize(size));
+                    iced_term::Event::ClipboardLoadRequested(id, formatter) => {
+                        return iced::clipboard::read(move |contents| {
+                            Message::ClipboardRead(
+                                id,
+                                formatter.clone(),
+                                contents,
+                            )
+                        });
+                    },
+                    event => {
+                        if let Some(tab) = self.tabs.get_mut(&event.id()) {
+                            tab.handle_event(event);
+                        }
                     },
-                    _ => {},
                 };
 
+                Command::none()
+            },
+            Message::ClipboardRead(id, formatter, contents) => {
+                let tab = self
+                    .tabs
+                    .get_mut(&id)
+                    .expect("tab with target id not found");
+                let reply = formatter.format(&contents.unwrap_or_default());
+                tab.update(iced_term::Command::ReplyToPTY(reply));
+
                 Command::none()
             },
         }
@@ -99,7 +122,7 @@ impl Application for App {
         let mut sb = vec![];
         for id in self.tabs.keys() {
             let tab = self.tabs.get(id).unwrap();
-            let sub = tab.data_subscription().map(Message::TermEvent);
+            let sub = tab.subscription().map(Message::TermEvent);
 
             sb.push(sub)
         }
@@ -107,7 +130,7 @@ impl Application for App {
         Subscription::batch(sb)
     }
 
-    fn view(&self) -> Element<Message, iced::Renderer> {
+    fn view(&self) -> Element<'_, Message, iced::Renderer> {
         let tab_id = 0;
         let tab = self
             .tabs
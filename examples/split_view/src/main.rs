@@ -47,11 +47,11 @@ impl Application for Example {
         let initial_pane_id = 0;
         let (panes, _) = pane_grid::State::new(Pane::new(initial_pane_id));
         let term_settings = iced_term::TermSettings {
-            font: iced_term::FontSettings { size: 14.0 },
-            backend: iced_term::BackendSettings {
-                shell: env!("SHELL").to_string(),
-                ..iced_term::BackendSettings::default()
+            font: iced_term::FontSettings {
+                size: 14.0,
+                ..iced_term::FontSettings::default()
             },
+            ..iced_term::TermSettings::default()
         };
         let tab =
             iced_term::Term::new(initial_pane_id as u64, term_settings.clone());
@@ -126,38 +126,10 @@ impl Application for Example {
                     self.focus = Some(sibling);
                 }
             },
-            Message::TermEvent(m) => {
-                match m {
-                    iced_term::Event::InputReceived(id, c) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::WriteToPTY(c))
-                    },
-                    iced_term::Event::DataUpdated(id, data) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::RenderData(data))
-                    },
-                    iced_term::Event::ContainerScrolled(id, delta) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::Scroll(delta as i32))
-                    },
-                    iced_term::Event::Resized(id, size) => {
-                        let tab = self
-                            .tabs
-                            .get_mut(&id)
-                            .expect("tab with target id not found");
-                        tab.update(iced_term::Command::Resize(size));
-                    },
-                    _ => {},
-                };
+            Message::TermEvent(event) => {
+                if let Some(tab) = self.tabs.get_mut(&event.id()) {
+                    tab.handle_event(event);
+                }
             },
         }
 
@@ -168,7 +140,7 @@ impl Application for Example {
         let mut sb = vec![];
         for id in self.tabs.keys() {
             let tab = self.tabs.get(id).unwrap();
-            let sub = tab.data_subscription().map(Message::TermEvent);
+            let sub = tab.subscription().map(Message::TermEvent);
 
             sb.push(sub)
         }
@@ -176,7 +148,7 @@ impl Application for Example {
         Subscription::batch(sb)
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let focus = self.focus;
         let total_panes = self.panes.len();
 
@@ -0,0 +1,155 @@
+use iced::advanced::graphics::core::Element;
+use iced::widget::{button, column, container, row, text};
+use iced::{
+    executor, window, Application, Command, Length, Settings, Subscription,
+    Theme,
+};
+use iced_term::{self, Term, TermSettings};
+use std::collections::BTreeMap;
+
+const DEFAULT_TAB_TITLE: &str = "shell";
+
+fn main() -> iced::Result {
+    App::run(Settings {
+        antialiasing: true,
+        window: window::Settings {
+            size: (800, 600),
+            ..window::Settings::default()
+        },
+        ..Settings::default()
+    })
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    TermEvent(iced_term::Event),
+    NewTab,
+    SelectTab(u64),
+}
+
+struct Tab {
+    title: String,
+    term: Term,
+}
+
+struct App {
+    tabs: BTreeMap<u64, Tab>,
+    active_tab_id: u64,
+    next_tab_id: u64,
+    term_settings: TermSettings,
+}
+
+impl App {
+    fn open_tab(&mut self) {
+        let id = self.next_tab_id;
+        let term = match Term::try_new(id, self.term_settings.clone()) {
+            Ok(term) => term,
+            Err(err) => {
+                eprintln!("failed to open a new tab: {}", err);
+                return;
+            },
+        };
+        self.tabs.insert(
+            id,
+            Tab {
+                title: String::from(DEFAULT_TAB_TITLE),
+                term,
+            },
+        );
+        self.active_tab_id = id;
+        self.next_tab_id += 1;
+    }
+}
+
+impl Application for App {
+    type Message = Message;
+    type Theme = Theme;
+    type Executor = executor::Default;
+    type Flags = ();
+
+    fn new(_flags: ()) -> (Self, Command<Message>) {
+        let mut app = Self {
+            tabs: BTreeMap::new(),
+            active_tab_id: 0,
+            next_tab_id: 0,
+            term_settings: TermSettings::default(),
+        };
+        app.open_tab();
+
+        (app, Command::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("Tabs")
+    }
+
+    fn update(&mut self, message: Self::Message) -> iced::Command<Message> {
+        match message {
+            Message::TermEvent(event) => {
+                let id = event.id();
+                match event {
+                    iced_term::Event::TitleChanged(_, title) => {
+                        if let Some(tab) = self.tabs.get_mut(&id) {
+                            tab.title = title;
+                        }
+                    },
+                    iced_term::Event::TitleReset(_) => {
+                        if let Some(tab) = self.tabs.get_mut(&id) {
+                            tab.title = String::from(DEFAULT_TAB_TITLE);
+                        }
+                    },
+                    iced_term::Event::ProcessExited(_, _) => {
+                        self.tabs.remove(&id);
+                        match self.tabs.keys().next_back() {
+                            Some(last_id) => self.active_tab_id = *last_id,
+                            None => return window::close(),
+                        }
+                    },
+                    event => {
+                        if let Some(tab) = self.tabs.get_mut(&id) {
+                            tab.term.handle_event(event);
+                        }
+                    },
+                }
+            },
+            Message::NewTab => self.open_tab(),
+            Message::SelectTab(id) => self.active_tab_id = id,
+        }
+
+        Command::none()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let subscriptions = self
+            .tabs
+            .values()
+            .map(|tab| tab.term.subscription().map(Message::TermEvent));
+
+        Subscription::batch(subscriptions)
+    }
+
+    fn view(&self) -> Element<'_, Message, iced::Renderer> {
+        let mut tab_bar = row![].spacing(4).padding(4);
+        for (id, tab) in &self.tabs {
+            let label = if *id == self.active_tab_id {
+                format!("[{}]", tab.title)
+            } else {
+                tab.title.clone()
+            };
+            tab_bar = tab_bar
+                .push(button(text(label)).on_press(Message::SelectTab(*id)));
+        }
+        tab_bar = tab_bar.push(button(text("+")).on_press(Message::NewTab));
+
+        let tab_view = match self.tabs.get(&self.active_tab_id) {
+            Some(tab) => tab.term.view().map(Message::TermEvent),
+            None => text("").into(),
+        };
+
+        column![
+            tab_bar,
+            container(tab_view).width(Length::Fill).height(Length::Fill)
+        ]
+        .into()
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Spawn(io::Error),
+    Read(io::Error),
+    Write(io::Error),
+    Signal(io::Error),
+}
+
+impl Error {
+    pub fn kind(&self) -> io::ErrorKind {
+        self.io_error().kind()
+    }
+
+    fn io_error(&self) -> &io::Error {
+        match self {
+            Error::Spawn(err)
+            | Error::Read(err)
+            | Error::Write(err)
+            | Error::Signal(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Spawn(err) => write!(f, "failed to spawn pty: {}", err),
+            Error::Read(err) => write!(f, "failed to read from pty: {}", err),
+            Error::Write(err) => write!(f, "failed to write to pty: {}", err),
+            Error::Signal(err) => {
+                write!(f, "failed to signal child process: {}", err)
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.io_error())
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+const MAX_IMAGES: usize = 256;
+// Same as the default storage quota of kitty.
+pub const MAX_IMAGE_BYTES: usize = 320 * 1024 * 1024;
+const MARKER_BASE: u32 = 0xF0000;
+const MARKER_RANGE: u64 = 0xFFFE;
+
+// Placements are tracked by a private use zero-width character stored in the
+// origin cell, so they scroll, reflow and get erased together with the text.
+pub fn marker(id: u64) -> char {
+    char::from_u32(MARKER_BASE + id as u32).expect("invalid image marker")
+}
+
+pub fn marker_id(c: char) -> Option<u64> {
+    let code = (c as u32).checked_sub(MARKER_BASE)?;
+    (u64::from(code) < MARKER_RANGE).then_some(u64::from(code))
+}
+
+#[derive(Debug)]
+pub struct InlineImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: ImageData,
+}
+
+impl InlineImage {
+    pub fn byte_len(&self) -> usize {
+        match &self.data {
+            ImageData::Rgba(data) => data.len(),
+            #[cfg(feature = "kitty-graphics")]
+            ImageData::Png(data) => data.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ImageData {
+    Rgba(Arc<[u8]>),
+    #[cfg(feature = "kitty-graphics")]
+    Png(Arc<[u8]>),
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderableImage {
+    pub id: u64,
+    pub line: i32,
+    pub column: usize,
+    pub image: Arc<InlineImage>,
+}
+
+#[derive(Default)]
+pub struct ImageStore {
+    images: HashMap<u64, Arc<InlineImage>>,
+    order: VecDeque<u64>,
+    bytes: usize,
+    next_id: u64,
+}
+
+impl ImageStore {
+    pub fn insert(&mut self, image: Arc<InlineImage>) -> u64 {
+        let id = self.next_id;
+        self.next_id = (self.next_id + 1) % MARKER_RANGE;
+        self.bytes += image.byte_len();
+        if let Some(replaced) = self.images.insert(id, image) {
+            self.bytes -= replaced.byte_len();
+            self.order.retain(|other| *other != id);
+        }
+        self.order.push_back(id);
+        while self.order.len() > MAX_IMAGES || self.bytes > MAX_IMAGE_BYTES {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(image) = self.images.remove(&oldest) {
+                self.bytes -= image.byte_len();
+            }
+        }
+
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<InlineImage>> {
+        self.images.get(&id).cloned()
+    }
+
+    #[cfg(feature = "kitty-graphics")]
+    pub fn remove(&mut self, id: u64) {
+        if let Some(image) = self.images.remove(&id) {
+            self.bytes -= image.byte_len();
+        }
+        self.order.retain(|other| *other != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_evicts_oldest_images_over_byte_budget() {
+        let chunk = MAX_IMAGE_BYTES / 4;
+        let image = Arc::new(InlineImage {
+            width: 1,
+            height: 1,
+            data: ImageData::Rgba(vec![0; chunk].into()),
+        });
+        let mut store = ImageStore::default();
+        let ids: Vec<u64> =
+            (0..6).map(|_| store.insert(image.clone())).collect();
+
+        assert!(store.bytes <= MAX_IMAGE_BYTES);
+        assert!(store.get(ids[0]).is_none());
+        assert!(store.get(ids[1]).is_none());
+        assert!(store.get(ids[2]).is_some());
+        assert!(store.get(ids[5]).is_some());
+    }
+}
@@ -0,0 +1,14 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridRange {
+    pub start_line: i32,
+    pub start_column: usize,
+    pub end_line: i32,
+    pub end_column: usize,
+}
+
+impl GridRange {
+    pub fn contains(&self, line: i32, column: usize) -> bool {
+        (line, column) >= (self.start_line, self.start_column)
+            && (line, column) <= (self.end_line, self.end_column)
+    }
+}
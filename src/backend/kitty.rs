@@ -0,0 +1,376 @@
+use crate::backend::graphics::{ImageData, InlineImage, MAX_IMAGE_BYTES};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+const MAX_PAYLOAD_BYTES: usize = 0x4000000;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const MAX_PLACEMENTS: usize = 1024;
+const MAX_IMAGES: usize = 256;
+
+pub enum KittyAction {
+    Place(Option<u32>, Arc<InlineImage>),
+    Delete(Vec<u64>),
+    Reply(String),
+}
+
+#[derive(Default)]
+pub struct KittyGraphics {
+    pending: Option<Transfer>,
+    images: HashMap<u32, Arc<InlineImage>>,
+    // Ids of the stored images, least recently used first.
+    image_order: VecDeque<u32>,
+    image_bytes: usize,
+    placements: Vec<(Option<u32>, u64)>,
+}
+
+struct Transfer {
+    control: Control,
+    payload: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Control {
+    action: u8,
+    format: u32,
+    width: u32,
+    height: u32,
+    id: Option<u32>,
+    more: bool,
+    quiet: u32,
+    medium: u8,
+    compression: Option<u8>,
+    delete: u8,
+}
+
+impl KittyGraphics {
+    pub fn handle(&mut self, command: &[u8]) -> Option<KittyAction> {
+        let command = command.strip_prefix(b"G")?;
+        let (control, payload) = match command.iter().position(|b| *b == b';') {
+            Some(index) => (&command[..index], &command[index + 1..]),
+            None => (command, &command[command.len()..]),
+        };
+        let control = parse_control(control);
+
+        if let Some(transfer) = self.pending.as_mut() {
+            if transfer.payload.len() + payload.len() <= MAX_PAYLOAD_BYTES {
+                transfer.payload.extend_from_slice(payload);
+            }
+            if control.more {
+                return None;
+            }
+
+            let transfer = self.pending.take()?;
+            return self.finish_transfer(transfer.control, &transfer.payload);
+        }
+
+        match control.action {
+            b't' | b'T' if control.more => {
+                self.pending = Some(Transfer {
+                    control,
+                    payload: payload.to_vec(),
+                });
+                None
+            },
+            b't' | b'T' => self.finish_transfer(control, payload),
+            b'p' => match control.id.and_then(|id| self.use_image(id)) {
+                Some(image) => Some(KittyAction::Place(control.id, image)),
+                None => reply(&control, "ENOENT:image not found"),
+            },
+            b'd' => {
+                // Only deleting everything and deleting by id are supported,
+                // other specifiers are ignored rather than widened to all.
+                let id = match control.delete {
+                    b'a' | b'A' => None,
+                    b'i' | b'I' => Some(control.id?),
+                    _ => return None,
+                };
+                // Lowercase specifiers only remove placements, the image data
+                // stays around for later `a=p` commands.
+                if control.delete.is_ascii_uppercase() {
+                    match id {
+                        Some(id) => self.remove_image(id),
+                        None => {
+                            self.images.clear();
+                            self.image_order.clear();
+                            self.image_bytes = 0;
+                        },
+                    }
+                }
+
+                let (deleted, kept) = self
+                    .placements
+                    .drain(..)
+                    .partition(|(image_id, _)| id.is_none() || *image_id == id);
+                self.placements = kept;
+                Some(KittyAction::Delete(
+                    deleted
+                        .into_iter()
+                        .map(|(_, placement)| placement)
+                        .collect(),
+                ))
+            },
+            b'q' => match decode(&control, payload) {
+                Ok(_) => reply(&control, "OK"),
+                Err(err) => reply(&control, err),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn placed(&mut self, id: Option<u32>, placement: u64) {
+        self.placements.push((id, placement));
+        if self.placements.len() > MAX_PLACEMENTS {
+            self.placements.remove(0);
+        }
+    }
+
+    fn store_image(&mut self, id: u32, image: Arc<InlineImage>) {
+        self.remove_image(id);
+        self.image_bytes += image.byte_len();
+        self.images.insert(id, image);
+        self.image_order.push_back(id);
+        while self.image_order.len() > MAX_IMAGES
+            || self.image_bytes > MAX_IMAGE_BYTES
+        {
+            match self.image_order.front() {
+                Some(&oldest) => self.remove_image(oldest),
+                None => break,
+            }
+        }
+    }
+
+    fn use_image(&mut self, id: u32) -> Option<Arc<InlineImage>> {
+        let image = self.images.get(&id)?.clone();
+        self.image_order.retain(|other| *other != id);
+        self.image_order.push_back(id);
+        Some(image)
+    }
+
+    fn remove_image(&mut self, id: u32) {
+        if let Some(image) = self.images.remove(&id) {
+            self.image_bytes -= image.byte_len();
+            self.image_order.retain(|other| *other != id);
+        }
+    }
+
+    fn finish_transfer(
+        &mut self,
+        control: Control,
+        payload: &[u8],
+    ) -> Option<KittyAction> {
+        let image = match decode(&control, payload) {
+            Ok(image) => Arc::new(image),
+            Err(err) => return reply(&control, err),
+        };
+
+        if let Some(id) = control.id {
+            self.store_image(id, image.clone());
+        }
+
+        if control.action == b'T' {
+            Some(KittyAction::Place(control.id, image))
+        } else {
+            reply(&control, "OK")
+        }
+    }
+}
+
+fn reply(control: &Control, message: &str) -> Option<KittyAction> {
+    let id = control.id?;
+    let is_error = message != "OK";
+    if control.quiet >= 2 || (control.quiet == 1 && !is_error) {
+        return None;
+    }
+
+    Some(KittyAction::Reply(format!(
+        "\x1b_Gi={};{}\x1b\\",
+        id, message
+    )))
+}
+
+fn parse_control(control: &[u8]) -> Control {
+    let mut parsed = Control {
+        action: b't',
+        format: 32,
+        medium: b'd',
+        delete: b'a',
+        ..Control::default()
+    };
+
+    for pair in control.split(|b| *b == b',') {
+        let [key, b'=', value @ ..] = pair else {
+            continue;
+        };
+        let number = || {
+            std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<u32>().ok())
+        };
+
+        match key {
+            b'a' => parsed.action = value.first().copied().unwrap_or(b't'),
+            b'f' => parsed.format = number().unwrap_or(32),
+            b's' => parsed.width = number().unwrap_or(0),
+            b'v' => parsed.height = number().unwrap_or(0),
+            b'i' => parsed.id = number(),
+            b'm' => parsed.more = number() == Some(1),
+            b'q' => parsed.quiet = number().unwrap_or(0),
+            b't' => parsed.medium = value.first().copied().unwrap_or(b'd'),
+            b'o' => parsed.compression = value.first().copied(),
+            b'd' => parsed.delete = value.first().copied().unwrap_or(b'a'),
+            _ => {},
+        }
+    }
+
+    parsed
+}
+
+fn decode(
+    control: &Control,
+    payload: &[u8],
+) -> Result<InlineImage, &'static str> {
+    if control.medium != b'd' {
+        return Err("ENOTSUPPORTED:only direct transmission is supported");
+    }
+    if control.compression.is_some() {
+        return Err("ENOTSUPPORTED:compression is not supported");
+    }
+
+    let data = decode_base64(payload).ok_or("EINVAL:invalid base64 data")?;
+    let (width, height) = (control.width, control.height);
+    let pixels = (width as usize) * (height as usize);
+    match control.format {
+        32 if pixels > 0 && data.len() == pixels * 4 => Ok(InlineImage {
+            width,
+            height,
+            data: ImageData::Rgba(data.into()),
+        }),
+        24 if pixels > 0 && data.len() == pixels * 3 => {
+            let rgba: Vec<u8> = data
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xff])
+                .collect();
+            Ok(InlineImage {
+                width,
+                height,
+                data: ImageData::Rgba(rgba.into()),
+            })
+        },
+        100 => {
+            let (width, height) =
+                png_size(&data).ok_or("EBADPNG:invalid png data")?;
+            Ok(InlineImage {
+                width,
+                height,
+                data: ImageData::Png(data.into()),
+            })
+        },
+        24 | 32 => Err("ENODATA:insufficient image data"),
+        _ => Err("EINVAL:unknown image format"),
+    }
+}
+
+fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    if !data.starts_with(PNG_SIGNATURE) || &data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+fn decode_base64(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in input {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSMIT: &[u8] = b"Ga=t,f=24,s=1,v=1,i=7,q=2;AAAA";
+
+    fn is_placed(action: Option<KittyAction>) -> bool {
+        matches!(action, Some(KittyAction::Place(Some(7), _)))
+    }
+
+    #[test]
+    fn lowercase_delete_keeps_image_data() {
+        let mut kitty = KittyGraphics::default();
+        assert!(kitty.handle(TRANSMIT).is_none());
+        assert!(is_placed(kitty.handle(b"Ga=p,i=7")));
+        kitty.placed(Some(7), 1);
+
+        let deleted = kitty.handle(b"Ga=d,d=a");
+        assert!(
+            matches!(deleted, Some(KittyAction::Delete(ids)) if ids == [1])
+        );
+        assert!(is_placed(kitty.handle(b"Ga=p,i=7")));
+    }
+
+    #[test]
+    fn unsupported_delete_is_ignored() {
+        let mut kitty = KittyGraphics::default();
+        assert!(kitty.handle(TRANSMIT).is_none());
+        kitty.placed(Some(7), 1);
+
+        assert!(kitty.handle(b"Ga=d,d=C").is_none());
+        assert!(kitty.handle(b"Ga=d,d=Z").is_none());
+        assert!(is_placed(kitty.handle(b"Ga=p,i=7")));
+        let deleted = kitty.handle(b"Ga=d,d=i,i=7");
+        assert!(
+            matches!(deleted, Some(KittyAction::Delete(ids)) if ids == [1])
+        );
+    }
+
+    #[test]
+    fn least_recently_used_images_are_evicted() {
+        let mut kitty = KittyGraphics::default();
+        for id in 0..=MAX_IMAGES {
+            let command = format!("Ga=t,f=24,s=1,v=1,i={},q=2;AAAA", id);
+            assert!(kitty.handle(command.as_bytes()).is_none());
+            if id == 1 {
+                assert!(kitty.handle(b"Ga=p,i=0").is_some());
+            }
+        }
+
+        assert_eq!(kitty.images.len(), MAX_IMAGES);
+        assert_eq!(kitty.image_bytes, MAX_IMAGES * 4);
+        assert!(kitty.images.contains_key(&0));
+        assert!(!kitty.images.contains_key(&1));
+    }
+
+    #[test]
+    fn uppercase_delete_frees_image_data() {
+        let mut kitty = KittyGraphics::default();
+        assert!(kitty.handle(TRANSMIT).is_none());
+
+        kitty.handle(b"Ga=d,d=I,i=7");
+        assert!(matches!(
+            kitty.handle(b"Ga=p,i=7"),
+            Some(KittyAction::Reply(reply)) if reply.contains("ENOENT")
+        ));
+    }
+}
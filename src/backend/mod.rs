@@ -1,7 +1,34 @@
+mod error;
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+mod graphics;
+mod grid_range;
+#[cfg(feature = "kitty-graphics")]
+mod kitty;
+mod observer;
+mod prompt;
 mod pty;
 mod renderable_cell;
+mod renderable_content;
+mod scroll_state;
 mod settings;
+#[cfg(feature = "sixel")]
+mod sixel;
+mod terminal_event;
 
+pub use alacritty_terminal::term::TermMode;
+pub use error::{Error, Result};
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+pub use graphics::{ImageData, RenderableImage};
+pub use grid_range::GridRange;
+pub use prompt::CommandRegion;
 pub use pty::Pty;
 pub use renderable_cell::RenderableCell;
-pub use settings::BackendSettings;
+pub use renderable_content::{
+    CursorShape, RenderableContent, RenderableCursor, RenderableLink,
+};
+pub use scroll_state::ScrollState;
+pub use settings::{BackendSettings, BackendSettingsBuilder, Osc52};
+pub use terminal_event::{
+    ClipboardFormatter, ColorFormatter, DynamicColor, TerminalEvent,
+    TextAreaSizeFormatter,
+};
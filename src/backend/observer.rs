@@ -0,0 +1,232 @@
+use alacritty_terminal::vte::{Params, Parser, Perform};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+#[cfg(feature = "sixel")]
+const MAX_SIXEL_BYTES: usize = 0x1000000;
+#[cfg(feature = "kitty-graphics")]
+const MAX_APC_BYTES: usize = 0x1000000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObservedSequence {
+    SetBlink(bool),
+    WorkingDirectory(PathBuf),
+    SemanticPrompt(u8),
+    #[cfg(feature = "sixel")]
+    Sixel(Vec<u8>),
+    #[cfg(feature = "kitty-graphics")]
+    Apc(Vec<u8>),
+}
+
+// Every byte of output goes through this parser as well as alacritty's. The
+// vte state machine is table driven and the performer drops all but a few
+// sequences, so the second pass costs a table lookup per byte and doesn't
+// touch the grid.
+pub struct SequenceObserver {
+    parser: Parser,
+    performer: Performer,
+    #[cfg(feature = "kitty-graphics")]
+    apc: ApcScanner,
+}
+
+impl SequenceObserver {
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            performer: Performer::default(),
+            #[cfg(feature = "kitty-graphics")]
+            apc: ApcScanner::default(),
+        }
+    }
+
+    pub fn advance(&mut self, byte: u8) -> Option<ObservedSequence> {
+        // vte drops APC strings without reporting them, so they are picked
+        // out of the raw stream instead.
+        #[cfg(feature = "kitty-graphics")]
+        if let Some(data) = self.apc.advance(byte) {
+            return Some(ObservedSequence::Apc(data));
+        }
+
+        self.parser.advance(&mut self.performer, byte);
+        self.performer.sequence.take()
+    }
+}
+
+#[derive(Default)]
+struct Performer {
+    sequence: Option<ObservedSequence>,
+    #[cfg(feature = "sixel")]
+    sixel: Option<Vec<u8>>,
+}
+
+impl Perform for Performer {
+    fn csi_dispatch(
+        &mut self,
+        params: &Params,
+        intermediates: &[u8],
+        _ignore: bool,
+        action: char,
+    ) {
+        if action == 'm' && intermediates.is_empty() {
+            self.sequence = sgr_blink(params).map(ObservedSequence::SetBlink);
+        }
+    }
+
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            [b"7", uri @ ..] => {
+                self.sequence = working_directory(&uri.join(&b';'))
+                    .map(ObservedSequence::WorkingDirectory);
+            },
+            [b"133", kind, ..] => {
+                self.sequence =
+                    kind.first().copied().map(ObservedSequence::SemanticPrompt);
+            },
+            _ => {},
+        }
+    }
+
+    #[cfg(feature = "sixel")]
+    fn hook(
+        &mut self,
+        _params: &Params,
+        intermediates: &[u8],
+        _ignore: bool,
+        action: char,
+    ) {
+        if action == 'q' && intermediates.is_empty() {
+            self.sixel = Some(Vec::new());
+        }
+    }
+
+    #[cfg(feature = "sixel")]
+    fn put(&mut self, byte: u8) {
+        if let Some(data) = self.sixel.as_mut() {
+            if data.len() < MAX_SIXEL_BYTES {
+                data.push(byte);
+            }
+        }
+    }
+
+    #[cfg(feature = "sixel")]
+    fn unhook(&mut self) {
+        self.sequence = self.sixel.take().map(ObservedSequence::Sixel);
+    }
+}
+
+#[cfg(feature = "kitty-graphics")]
+#[derive(Default)]
+enum ApcScanner {
+    #[default]
+    Ground,
+    Escape,
+    String(Vec<u8>),
+    StringEscape(Vec<u8>),
+}
+
+#[cfg(feature = "kitty-graphics")]
+impl ApcScanner {
+    fn advance(&mut self, byte: u8) -> Option<Vec<u8>> {
+        let (state, data) = match (std::mem::take(self), byte) {
+            (ApcScanner::Ground | ApcScanner::Escape, 0x1b) => {
+                (ApcScanner::Escape, None)
+            },
+            (ApcScanner::Escape, b'_') => {
+                (ApcScanner::String(Vec::new()), None)
+            },
+            (ApcScanner::String(data), 0x1b) => {
+                (ApcScanner::StringEscape(data), None)
+            },
+            (ApcScanner::String(mut data), byte) => {
+                if data.len() < MAX_APC_BYTES {
+                    data.push(byte);
+                }
+                (ApcScanner::String(data), None)
+            },
+            (ApcScanner::StringEscape(data), b'\\') => {
+                (ApcScanner::Ground, Some(data))
+            },
+            (ApcScanner::StringEscape(_), b'_') => {
+                (ApcScanner::String(Vec::new()), None)
+            },
+            _ => (ApcScanner::Ground, None),
+        };
+
+        *self = state;
+        data
+    }
+}
+
+fn sgr_blink(params: &Params) -> Option<bool> {
+    if params.is_empty() {
+        return Some(false);
+    }
+
+    let mut blink = None;
+    let mut params = params.iter();
+
+    while let Some(param) = params.next() {
+        match param {
+            [0] => blink = Some(false),
+            [5] | [6] => blink = Some(true),
+            [25] => blink = Some(false),
+            [38] | [48] | [58] => match params.next() {
+                Some([5]) => {
+                    params.next();
+                },
+                Some([2]) => {
+                    params.nth(2);
+                },
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+
+    blink
+}
+
+// OSC 7 reports the directory as `file://host/path`. Directories on other
+// hosts, such as from an ssh session, don't exist here and are skipped.
+fn working_directory(uri: &[u8]) -> Option<PathBuf> {
+    let rest = uri.strip_prefix(b"file://")?;
+    let index = rest.iter().position(|b| *b == b'/')?;
+    let (host, path) = rest.split_at(index);
+    if !host.is_empty()
+        && host != b"localhost"
+        && host != hostname()?.as_slice()
+    {
+        return None;
+    }
+
+    Some(PathBuf::from(OsStr::from_bytes(&percent_decode(path)?)))
+}
+
+fn percent_decode(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut bytes = input.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != b'%' {
+            output.push(byte);
+            continue;
+        }
+
+        let hex = [*bytes.next()?, *bytes.next()?];
+        let hex = std::str::from_utf8(&hex).ok()?;
+        output.push(u8::from_str_radix(hex, 16).ok()?);
+    }
+
+    Some(output)
+}
+
+fn hostname() -> Option<Vec<u8>> {
+    let mut buf = [0u8; 256];
+    let res = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if res != 0 {
+        return None;
+    }
+
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    Some(buf[..len].to_vec())
+}
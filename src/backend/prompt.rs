@@ -0,0 +1,90 @@
+use alacritty_terminal::grid::{Dimensions, Grid};
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::cell::Cell;
+
+const PROMPT_BASE: u32 = 0x100000;
+const COMMAND_BASE: u32 = 0x108000;
+const MARKER_RANGE: u32 = 0x7ffe;
+
+// OSC 133 regions are tracked by a private use zero-width character kept in
+// the cursor template while the region is being written, so every cell of
+// the prompt or command carries it and it scrolls and reflows with the
+// text. Each prompt gets its own character to tell adjacent prompts apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Marker {
+    Prompt(u32),
+    Command(u32),
+}
+
+impl Marker {
+    fn from_char(c: char) -> Option<Self> {
+        match c as u32 {
+            code @ PROMPT_BASE..=0x107ffd => {
+                Some(Marker::Prompt(code - PROMPT_BASE))
+            },
+            code @ COMMAND_BASE..=0x10fffd => {
+                Some(Marker::Command(code - COMMAND_BASE))
+            },
+            _ => None,
+        }
+    }
+}
+
+pub fn prompt_marker(id: u32) -> char {
+    char::from_u32(PROMPT_BASE + id % MARKER_RANGE).expect("invalid marker")
+}
+
+pub fn command_marker(id: u32) -> char {
+    char::from_u32(COMMAND_BASE + id % MARKER_RANGE).expect("invalid marker")
+}
+
+pub fn is_marker(c: char) -> bool {
+    Marker::from_char(c).is_some()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommandRegion {
+    /// First line of the prompt.
+    pub prompt_line: i32,
+    /// First line of the command typed at the prompt, if any.
+    pub command_line: Option<i32>,
+    /// Last line before the next prompt, covering the command output.
+    pub end_line: i32,
+}
+
+pub fn command_regions(grid: &Grid<Cell>) -> Vec<CommandRegion> {
+    let mut regions: Vec<CommandRegion> = Vec::new();
+    let mut current = None;
+    let bottom = grid.bottommost_line().0;
+    for line in grid.topmost_line().0..=bottom {
+        let row = &grid[Line(line)];
+        let markers = (0..grid.columns())
+            .filter_map(|column| row[Column(column)].zerowidth())
+            .flatten()
+            .filter_map(|c| Marker::from_char(*c));
+
+        for marker in markers {
+            match marker {
+                Marker::Prompt(id) if current != Some(id) => {
+                    if let Some(region) = regions.last_mut() {
+                        region.end_line = line - 1;
+                    }
+                    regions.push(CommandRegion {
+                        prompt_line: line,
+                        command_line: None,
+                        end_line: bottom,
+                    });
+                    current = Some(id);
+                },
+                Marker::Command(id) if current == Some(id) => {
+                    if let Some(region) = regions.last_mut() {
+                        region.command_line.get_or_insert(line);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    regions
+}
@@ -1,72 +1,248 @@
-use crate::backend::BackendSettings;
-use crate::backend::RenderableCell;
-use alacritty_terminal::event::{EventListener, OnResize, WindowSize};
-use alacritty_terminal::grid::Scroll;
-use alacritty_terminal::term::{cell, test::TermSize};
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+use crate::backend::graphics::{self, ImageStore, InlineImage};
+#[cfg(feature = "kitty-graphics")]
+use crate::backend::kitty::{KittyAction, KittyGraphics};
+use crate::backend::observer::{ObservedSequence, SequenceObserver};
+use crate::backend::prompt;
+use crate::backend::renderable_cell::BLINK_MARKER;
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+use crate::backend::RenderableImage;
+use crate::backend::{
+    BackendSettings, CommandRegion, DynamicColor, Error, Result, ScrollState,
+};
+use crate::backend::{
+    GridRange, RenderableCell, RenderableContent, RenderableCursor,
+    RenderableLink, TerminalEvent,
+};
+use alacritty_terminal::event::{Event, EventListener, OnResize, WindowSize};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::index::{Column, Direction, Line, Point, Side};
+use alacritty_terminal::selection::{Selection, SelectionType};
+use alacritty_terminal::term::color::{self, Colors};
+use alacritty_terminal::term::search::{Match, RegexIter, RegexSearch};
+use alacritty_terminal::term::{
+    cell, test::TermSize, viewport_to_point, TermDamage, TermMode,
+};
 use alacritty_terminal::tty::EventedReadWrite;
-use alacritty_terminal::vte::ansi;
+use alacritty_terminal::vte::ansi::{self, Handler};
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs::File;
-use std::io::Result;
-use std::io::Write;
-use tokio::io::AsyncReadExt;
-use tokio::time::sleep;
+use std::io::{self, ErrorKind};
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex, MutexGuard, PoisonError};
+use tokio::io::unix::AsyncFd;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Mutex;
+
+const READ_BUFFER_SIZE: usize = 0x10000;
+const MAX_READ_BYTES: usize = 0x100000;
+const DEFAULT_CELL_WIDTH: u16 = 13;
+const DEFAULT_CELL_HEIGHT: u16 = 20;
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+const IMAGE_LOOKBACK_LINES: i32 = 200;
+const URL_REGEX: &str = "(ipfs:|ipns:|magnet:|mailto:|gemini://|gopher://|https://|http://|news:|file:|git://|ssh:|ftp://)[^\u{0000}-\u{001F}\u{007F}-\u{009F}<>\"\\s{-}\\^⟨⟩`]+";
 
 pub struct Pty {
     _id: u64,
-    pty: alacritty_terminal::tty::Pty,
+    child: Option<ChildPty>,
     term: alacritty_terminal::Term<EventProxy>,
-    reader: File,
+    events: Arc<Mutex<UnboundedReceiver<TerminalEvent>>>,
+    event_sender: UnboundedSender<TerminalEvent>,
     parser: ansi::Processor,
+    observer: SequenceObserver,
+    url_regex: RegexSearch,
+    cell_size: (u16, u16),
+    has_new_output_below: bool,
+    reported_directory: Option<PathBuf>,
+    reported_colors: Colors,
+    prompt_id: u32,
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    images: ImageStore,
+    #[cfg(feature = "kitty-graphics")]
+    kitty: KittyGraphics,
+}
+
+struct ChildPty {
+    pty: alacritty_terminal::tty::Pty,
+    reader: File,
+}
+
+pub struct PtyReader {
+    file: File,
+    fd: Option<AsyncFd<File>>,
+    pid: u32,
+}
+
+impl PtyReader {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
 }
 
 impl Pty {
     pub fn new(id: u64, settings: BackendSettings) -> Result<Self> {
         let pty_config = alacritty_terminal::tty::Options {
             shell: Some(alacritty_terminal::tty::Shell::new(
-                settings.shell,
-                vec![],
+                settings.shell.clone(),
+                settings.shell_args.clone(),
             )),
+            working_directory: settings
+                .working_directory
+                .clone()
+                .filter(|path| path.is_dir()),
             ..alacritty_terminal::tty::Options::default()
         };
-        let config = alacritty_terminal::term::Config::default();
         let window_size = alacritty_terminal::event::WindowSize {
-            cell_width: 13,
-            cell_height: 20,
+            cell_width: DEFAULT_CELL_WIDTH,
+            cell_height: DEFAULT_CELL_HEIGHT,
             num_cols: settings.cols,
             num_lines: settings.rows,
         };
 
-        let mut pty =
-            alacritty_terminal::tty::new(&pty_config, window_size, id)?;
+        let mut pty = {
+            let _env = ScopedEnv::set(&settings.env);
+            alacritty_terminal::tty::new(&pty_config, window_size, id)
+                .map_err(Error::Spawn)?
+        };
+        let reader = pty.reader().try_clone().map_err(Error::Spawn)?;
+        let mut backend = Self::new_headless(id, settings);
+        backend.child = Some(ChildPty { pty, reader });
+
+        Ok(backend)
+    }
+
+    pub fn new_headless(id: u64, settings: BackendSettings) -> Self {
+        let config = alacritty_terminal::term::Config {
+            scrolling_history: settings.scrollback_lines,
+            semantic_escape_chars: settings.semantic_escape_chars,
+            osc52: settings.osc52,
+            ..alacritty_terminal::term::Config::default()
+        };
         let term_size =
             TermSize::new(settings.cols as usize, settings.rows as usize);
-        let reader = pty.reader().try_clone()?;
+        let (event_sender, event_receiver) = mpsc::unbounded_channel();
 
-        Ok(Self {
+        Self {
             _id: id,
-            pty,
-            reader,
+            child: None,
             term: alacritty_terminal::Term::new(
                 config,
                 &term_size,
-                EventProxy {},
+                EventProxy {
+                    sender: event_sender.clone(),
+                },
             ),
+            events: Arc::new(Mutex::new(event_receiver)),
+            event_sender,
             parser: ansi::Processor::new(),
-        })
+            observer: SequenceObserver::new(),
+            url_regex: RegexSearch::new(URL_REGEX).expect("invalid url regex"),
+            cell_size: (DEFAULT_CELL_WIDTH, DEFAULT_CELL_HEIGHT),
+            has_new_output_below: false,
+            reported_directory: None,
+            reported_colors: Colors::default(),
+            prompt_id: 0,
+            #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+            images: ImageStore::default(),
+            #[cfg(feature = "kitty-graphics")]
+            kitty: KittyGraphics::default(),
+        }
+    }
+
+    pub async fn read(reader: &mut PtyReader) -> Result<Vec<u8>> {
+        Self::read_available(reader).await.map_err(Error::Read)
     }
 
-    pub async fn read(reader: &File) -> Option<Vec<u8>> {
-        let mut file = tokio::fs::File::from(reader.try_clone().unwrap());
-        let mut buf = Vec::new();
-        if (file.read_buf(&mut buf).await).is_ok() {
-            return Some(buf);
+    async fn read_available(reader: &mut PtyReader) -> io::Result<Vec<u8>> {
+        let fd = match reader.fd {
+            Some(ref fd) => fd,
+            None => reader.fd.insert(AsyncFd::new(reader.file.try_clone()?)?),
         };
 
-        if buf.is_empty() {
-            sleep(std::time::Duration::from_millis(1)).await;
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+        let mut data = Vec::new();
+        loop {
+            let mut guard = fd.readable().await?;
+            while data.len() < MAX_READ_BYTES {
+                let result =
+                    match guard.try_io(|fd| fd.get_ref().read(&mut buf)) {
+                        Ok(result) => result,
+                        Err(_would_block) => break,
+                    };
+
+                match result {
+                    Ok(0) if data.is_empty() => {
+                        return Err(io::Error::from(ErrorKind::UnexpectedEof))
+                    },
+                    Ok(n) if n > 0 => data.extend_from_slice(&buf[..n]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {},
+                    Err(err) if data.is_empty() => return Err(err),
+                    _ => return Ok(data),
+                }
+            }
+
+            if !data.is_empty() {
+                return Ok(data);
+            }
         }
+    }
 
-        None
+    pub async fn wait_exit_code(pid: u32) -> Option<i32> {
+        tokio::task::spawn_blocking(move || wait_pid(pid))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    pub fn child_pid(&self) -> Option<u32> {
+        let pid = self.child.as_ref()?.pty.child().id();
+        (!has_exited(pid)).then_some(pid)
+    }
+
+    pub fn foreground_process(&self) -> Option<String> {
+        process_name(self.foreground_pgid()?)
+    }
+
+    fn foreground_pgid(&self) -> Option<u32> {
+        let child = self.child.as_ref()?;
+        let pgid = unsafe { libc::tcgetpgrp(child.reader.as_raw_fd()) };
+        (pgid > 0).then_some(pgid as u32)
+    }
+
+    /// The directory last reported by the shell through OSC 7, otherwise
+    /// the working directory of the child process where the platform
+    /// allows reading it.
+    pub fn working_directory(&self) -> Option<PathBuf> {
+        self.reported_directory
+            .clone()
+            .or_else(|| process_directory(self.child_pid()?))
+    }
+
+    /// Sends `signal` to the process group of the shell and to the
+    /// foreground job. With job control the shell starts every job in a
+    /// group of its own, which signalling the shell alone wouldn't reach.
+    pub fn send_signal(&self, signal: i32) -> Result<()> {
+        let Some(pid) = self.child_pid() else {
+            return Ok(());
+        };
+
+        if let Some(pgid) = self.foreground_pgid().filter(|pgid| *pgid != pid) {
+            match signal_process_group(pgid, signal) {
+                // The job may have exited since the group was looked up.
+                Err(err) if err.raw_os_error() != Some(libc::ESRCH) => {
+                    return Err(Error::Signal(err))
+                },
+                _ => {},
+            }
+        }
+        signal_process_group(pid, signal).map_err(Error::Signal)
+    }
+
+    pub fn kill(&self) -> Result<()> {
+        self.send_signal(libc::SIGKILL)
     }
 
     pub fn resize(
@@ -75,81 +251,1019 @@ impl Pty {
         cols: u16,
         font_width: f32,
         font_height: f32,
-    ) -> Vec<RenderableCell> {
-        if rows > 0 && cols > 0 {
+    ) -> RenderableContent {
+        let cell_size = (font_width.round() as u16, font_height.round() as u16);
+        let grid_changed = usize::from(rows) != self.term.screen_lines()
+            || usize::from(cols) != self.term.columns();
+        if rows > 0 && cols > 0 && (grid_changed || cell_size != self.cell_size)
+        {
             let size = WindowSize {
-                cell_width: font_width as u16,
-                cell_height: font_height as u16,
+                cell_width: cell_size.0,
+                cell_height: cell_size.1,
                 num_cols: cols,
                 num_lines: rows,
             };
 
-            self.pty.on_resize(size);
-            self.term.resize(TermSize::new(
-                size.num_cols as usize,
-                size.num_lines as usize,
-            ));
+            if let Some(child) = self.child.as_mut() {
+                child.pty.on_resize(size);
+            }
+            if grid_changed {
+                self.term.resize(TermSize::new(
+                    size.num_cols as usize,
+                    size.num_lines as usize,
+                ));
+            }
+            self.cell_size = cell_size;
+        }
+
+        self.cells()
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.term.columns() as u16, self.term.screen_lines() as u16)
+    }
+
+    pub fn window_size(&self) -> WindowSize {
+        let (num_cols, num_lines) = self.size();
+        WindowSize {
+            cell_width: self.cell_size.0,
+            cell_height: self.cell_size.1,
+            num_cols,
+            num_lines,
+        }
+    }
+
+    pub fn scroll(&mut self, delta_value: i32) -> RenderableContent {
+        self.scroll_display(Scroll::Delta(delta_value))
+    }
+
+    pub fn scroll_to_top(&mut self) -> RenderableContent {
+        self.scroll_display(Scroll::Top)
+    }
+
+    pub fn scroll_to_bottom(&mut self) -> RenderableContent {
+        self.scroll_display(Scroll::Bottom)
+    }
+
+    pub fn scroll_to_prev_prompt(&mut self) -> RenderableContent {
+        let top = -(self.term.grid().display_offset() as i32);
+        let prompt = self
+            .command_regions()
+            .into_iter()
+            .rev()
+            .map(|region| region.prompt_line)
+            .find(|line| *line < top);
+
+        match prompt {
+            Some(line) => self.scroll_display(Scroll::Delta(top - line)),
+            None => self.cells(),
+        }
+    }
+
+    pub fn scroll_to_next_prompt(&mut self) -> RenderableContent {
+        let top = -(self.term.grid().display_offset() as i32);
+        let prompt = self
+            .command_regions()
+            .into_iter()
+            .map(|region| region.prompt_line)
+            .find(|line| *line > top && *line <= 0);
+
+        match prompt {
+            Some(line) => self.scroll_display(Scroll::Delta(top - line)),
+            None => self.scroll_display(Scroll::Bottom),
+        }
+    }
+
+    fn scroll_display(&mut self, scroll: Scroll) -> RenderableContent {
+        if !self.is_alt_screen() {
+            self.term.scroll_display(scroll);
+        }
+        if self.term.grid().display_offset() == 0 {
+            self.has_new_output_below = false;
+        }
+
+        self.cells()
+    }
+
+    pub fn scroll_state(&self) -> ScrollState {
+        let grid = self.term.grid();
+        ScrollState {
+            display_offset: grid.display_offset(),
+            total_lines: grid.total_lines(),
+            visible_lines: grid.screen_lines(),
+            has_new_output_below: self.has_new_output_below
+                && grid.display_offset() != 0,
         }
+    }
+
+    pub fn is_alt_screen(&self) -> bool {
+        self.term.mode().contains(TermMode::ALT_SCREEN)
+    }
+
+    pub fn bracketed_paste_enabled(&self) -> bool {
+        self.term.mode().contains(TermMode::BRACKETED_PASTE)
+    }
+
+    pub fn mode(&self) -> TermMode {
+        *self.term.mode()
+    }
 
+    pub fn start_selection(
+        &mut self,
+        ty: SelectionType,
+        line: usize,
+        column: usize,
+        side: Side,
+    ) -> RenderableContent {
+        let point = self.viewport_point(line, column);
+        self.term.selection = Some(Selection::new(ty, point, side));
         self.cells()
     }
 
-    pub fn scroll(&mut self, delta_value: i32) -> Vec<RenderableCell> {
-        let scroll = Scroll::Delta(delta_value);
-        self.term.scroll_display(scroll);
+    pub fn update_selection(
+        &mut self,
+        line: usize,
+        column: usize,
+        side: Side,
+    ) -> RenderableContent {
+        let point = self.viewport_point(line, column);
+        if let Some(selection) = self.term.selection.as_mut() {
+            selection.update(point, side);
+        }
+
         self.cells()
     }
 
-    pub fn reader(&self) -> File {
-        self.reader.try_clone().unwrap()
+    pub fn selection_text(&self) -> Option<String> {
+        let text: String = self
+            .term
+            .selection_to_string()?
+            .chars()
+            .filter(|c| !is_marker(*c))
+            .collect();
+        let text = text
+            .split('\n')
+            .map(str::trim_end)
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        Some(text)
+    }
+
+    pub fn command_regions(&self) -> Vec<CommandRegion> {
+        prompt::command_regions(self.term.grid())
+    }
+
+    pub fn selection_range(&self) -> Option<GridRange> {
+        let range = self.term.selection.as_ref()?.to_range(&self.term)?;
+        Some(GridRange {
+            start_line: range.start.line.0,
+            start_column: range.start.column.0,
+            end_line: range.end.line.0,
+            end_column: range.end.column.0,
+        })
+    }
+
+    fn viewport_point(&self, line: usize, column: usize) -> Point {
+        let line = line.min(self.term.screen_lines().saturating_sub(1));
+        let column = column.min(self.term.columns().saturating_sub(1));
+        viewport_to_point(
+            self.term.grid().display_offset(),
+            Point::new(line, Column(column)),
+        )
+    }
+
+    pub fn reader(&self) -> Option<PtyReader> {
+        let child = self.child.as_ref()?;
+        let file = child.reader.try_clone().ok()?;
+        Some(PtyReader {
+            file,
+            fd: None,
+            pid: child.pty.child().id(),
+        })
+    }
+
+    pub fn events(&self) -> Arc<Mutex<UnboundedReceiver<TerminalEvent>>> {
+        self.events.clone()
     }
 
-    pub fn update(&mut self, data: Vec<u8>) -> Vec<RenderableCell> {
+    pub fn update(&mut self, data: Vec<u8>) {
         data.iter().for_each(|item| {
             self.parser.advance(&mut self.term, *item);
+            if let Some(sequence) = self.observer.advance(*item) {
+                self.apply_observed_sequence(sequence);
+            }
         });
 
+        if !data.is_empty() && self.term.grid().display_offset() != 0 {
+            self.has_new_output_below = true;
+        }
+        self.report_color_changes();
+    }
+
+    // alacritty keeps the colors set by the program but leaves drawing them
+    // to the frontend, so changes are forwarded like the title.
+    fn report_color_changes(&mut self) {
+        let colors = *self.term.colors();
+        for index in 0..color::COUNT {
+            if colors[index] == self.reported_colors[index] {
+                continue;
+            }
+            if let Some(color) = DynamicColor::from_index(index) {
+                let event = TerminalEvent::ColorChanged(color, colors[index]);
+                let _ = self.event_sender.send(event);
+            }
+        }
+        self.reported_colors = colors;
+    }
+
+    fn apply_observed_sequence(&mut self, sequence: ObservedSequence) {
+        match sequence {
+            ObservedSequence::SetBlink(blink) => {
+                let marker = blink.then_some(BLINK_MARKER);
+                self.set_template_marker(|c| c == BLINK_MARKER, marker);
+            },
+            ObservedSequence::WorkingDirectory(path) => {
+                if self.reported_directory.as_ref() != Some(&path) {
+                    let event =
+                        TerminalEvent::WorkingDirectoryChanged(path.clone());
+                    let _ = self.event_sender.send(event);
+                    self.reported_directory = Some(path);
+                }
+            },
+            ObservedSequence::SemanticPrompt(kind) => {
+                let marker = match kind {
+                    b'A' => {
+                        self.prompt_id = self.prompt_id.wrapping_add(1);
+                        Some(prompt::prompt_marker(self.prompt_id))
+                    },
+                    b'B' => Some(prompt::command_marker(self.prompt_id)),
+                    b'C' | b'D' => None,
+                    _ => return,
+                };
+
+                self.set_template_marker(prompt::is_marker, marker);
+            },
+            #[cfg(feature = "sixel")]
+            ObservedSequence::Sixel(data) => {
+                if let Some(image) = crate::backend::sixel::decode(&data) {
+                    self.place_image(Arc::new(image));
+                }
+            },
+            #[cfg(feature = "kitty-graphics")]
+            ObservedSequence::Apc(data) => match self.kitty.handle(&data) {
+                Some(KittyAction::Place(image_id, image)) => {
+                    let placement = self.place_image(image);
+                    self.kitty.placed(image_id, placement);
+                },
+                Some(KittyAction::Delete(placements)) => {
+                    for placement in placements {
+                        self.images.remove(placement);
+                    }
+                },
+                Some(KittyAction::Reply(reply)) => {
+                    let _ = self.write_reply(&reply);
+                },
+                None => {},
+            },
+        }
+    }
+
+    // Swaps the markers matching `replaced` in the cursor template for
+    // `marker`, leaving the ones tracked by other features in place.
+    fn set_template_marker(
+        &mut self,
+        replaced: impl Fn(char) -> bool,
+        marker: Option<char>,
+    ) {
+        let template = &mut self.term.grid_mut().cursor.template;
+        let kept: Vec<char> = template
+            .zerowidth()
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .filter(|c| !replaced(*c))
+            .collect();
+
+        let has_markers = !kept.is_empty() || marker.is_some();
+        template.clear_wide();
+        for c in kept.into_iter().chain(marker) {
+            template.push_zerowidth(c);
+        }
+        if !has_markers
+            && template.underline_color().is_none()
+            && template.hyperlink().is_none()
+        {
+            // Nothing else is kept in the extra storage, and cells written
+            // from the template shouldn't carry an empty one.
+            template.extra = None;
+        }
+    }
+
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    fn place_image(&mut self, image: Arc<InlineImage>) -> u64 {
+        let cell_height = u32::from(self.cell_size.1.max(1));
+        let rows = image.height.div_ceil(cell_height);
+        let id = self.images.insert(image);
+        let grid = self.term.grid_mut();
+        let point = grid.cursor.point;
+        grid[point].push_zerowidth(graphics::marker(id));
+        for _ in 0..rows {
+            self.term.linefeed();
+        }
+        self.term.carriage_return();
+
+        id
+    }
+
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    fn visible_images(&self, display_offset: usize) -> Vec<RenderableImage> {
+        let grid = self.term.grid();
+        let top = -(display_offset as i32);
+        let bottom = top + self.term.screen_lines() as i32 - 1;
+        let first = (top - IMAGE_LOOKBACK_LINES).max(grid.topmost_line().0);
+        let mut images = Vec::new();
+        for line in first..=bottom {
+            let row = &grid[Line(line)];
+            for column in 0..grid.columns() {
+                let Some(zerowidth) = row[Column(column)].zerowidth() else {
+                    continue;
+                };
+
+                for id in
+                    zerowidth.iter().filter_map(|c| graphics::marker_id(*c))
+                {
+                    if let Some(image) = self.images.get(id) {
+                        images.push(RenderableImage {
+                            id,
+                            line,
+                            column,
+                            image,
+                        });
+                    }
+                }
+            }
+        }
+
+        images
+    }
+
+    pub fn reset(&mut self) -> RenderableContent {
+        self.term.reset_state();
+        self.parser = ansi::Processor::new();
+        self.observer = SequenceObserver::new();
+        #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+        self.images.clear();
+        #[cfg(feature = "kitty-graphics")]
+        {
+            self.kitty = KittyGraphics::default();
+        }
+        self.report_color_changes();
         self.cells()
     }
 
-    pub fn write_to_pty(&mut self, c: char) {
+    pub fn clear_scrollback(&mut self) -> RenderableContent {
+        self.term.clear_screen(ansi::ClearMode::Saved);
+        self.cells()
+    }
+
+    pub fn grid_text(&self) -> Vec<String> {
+        let grid = self.term.grid();
+        (grid.topmost_line().0..=grid.bottommost_line().0)
+            .map(|line| {
+                let row = &grid[Line(line)];
+                let text: String = (0..grid.columns())
+                    .map(|column| &row[Column(column)])
+                    .filter(|cell| {
+                        !cell.flags.contains(cell::Flags::WIDE_CHAR_SPACER)
+                    })
+                    .map(|cell| cell.c)
+                    .collect();
+                text.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    pub fn search(
+        &self,
+        query: &str,
+        case_insensitive: bool,
+    ) -> Vec<GridRange> {
+        let flags = if case_insensitive { "(?i)" } else { "(?-i)" };
+        let pattern = format!("{}{}", flags, escape_regex(query));
+        let mut regex = match RegexSearch::new(&pattern) {
+            Ok(regex) => regex,
+            Err(_) => return Vec::new(),
+        };
+
+        let grid = self.term.grid();
+        let start = Point::new(grid.topmost_line(), Column(0));
+        let end = Point::new(grid.bottommost_line(), grid.last_column());
+        RegexIter::new(start, end, Direction::Right, &self.term, &mut regex)
+            .map(|search_match| grid_range(&search_match))
+            .collect()
+    }
+
+    pub fn damage(&mut self) -> Option<Vec<usize>> {
+        let is_scrolled = self.term.grid().display_offset() != 0;
+        let lines = match self.term.damage() {
+            TermDamage::Partial(lines) if !is_scrolled => {
+                Some(lines.map(|bounds| bounds.line).collect())
+            },
+            _ => None,
+        };
+        self.term.reset_damage();
+
+        lines
+    }
+
+    pub fn write_to_pty(&mut self, c: char) -> Result<()> {
+        let mut buf = [0; 4];
+        self.write_str(c.encode_utf8(&mut buf))
+    }
+
+    pub fn write_str(&mut self, s: &str) -> Result<()> {
+        self.write_bytes(s.as_bytes())
+    }
+
+    pub fn paste(&mut self, text: &str) -> Result<()> {
+        if self.bracketed_paste_enabled() {
+            let text = text.replace('\x1b', "");
+            self.write_str(&format!("\x1b[200~{}\x1b[201~", text))
+        } else {
+            self.write_str(&text.replace("\r\n", "\r").replace('\n', "\r"))
+        }
+    }
+
+    pub fn write_reply(&mut self, reply: &str) -> Result<()> {
+        self.write_to_child(reply.as_bytes())
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
         self.term.scroll_display(Scroll::Bottom);
-        self.pty.writer().write_all(&[c as u8]).unwrap();
+        self.has_new_output_below = false;
+        self.write_to_child(bytes)
+    }
+
+    fn write_to_child(&mut self, bytes: &[u8]) -> Result<()> {
+        match self.child.as_mut() {
+            Some(child) => {
+                child.pty.writer().write_all(bytes).map_err(Error::Write)
+            },
+            None => Ok(()),
+        }
     }
 
-    pub fn cells(&self) -> Vec<RenderableCell> {
-        let mut res = vec![];
+    pub fn cells(&mut self) -> RenderableContent {
+        let mut cells = vec![];
         let content = self.term.renderable_content();
+        let cursor_point = content.cursor.point;
+        let mut cursor = RenderableCursor {
+            shape: content.cursor.shape.into(),
+            column: cursor_point.column.0,
+            line: cursor_point.line.0,
+            ..RenderableCursor::default()
+        };
 
         for item in content.display_iter {
             let point = item.point;
             let cell = item.cell;
             let mut fg = cell.fg;
             let mut bg = cell.bg;
+            let is_selected = content.selection.is_some_and(|selection| {
+                selection.contains_cell(
+                    &item,
+                    content.cursor.point,
+                    content.cursor.shape,
+                )
+            });
 
-            if cell.flags.contains(cell::Flags::INVERSE) {
+            if cell.flags.contains(cell::Flags::INVERSE) ^ is_selected {
                 std::mem::swap(&mut fg, &mut bg);
             }
 
-            res.push(RenderableCell {
+            if point == cursor_point {
+                cursor.content = cell.c;
+                cursor.fg = fg;
+                cursor.bg = bg;
+                cursor.flags = cell.flags;
+            }
+
+            cells.push(RenderableCell {
                 column: point.column.0,
                 line: point.line.0,
                 content: cell.c,
                 display_offset: content.display_offset,
                 fg,
                 bg,
+                flags: cell.flags,
+                underline_color: cell.underline_color(),
+                blinking: cell
+                    .zerowidth()
+                    .is_some_and(|chars| chars.contains(&BLINK_MARKER)),
+                hyperlink: cell
+                    .hyperlink()
+                    .map(|hyperlink| hyperlink.uri().to_string()),
+                is_selected,
             })
         }
 
-        res
+        let display_offset = content.display_offset;
+        RenderableContent {
+            cells,
+            cursor,
+            display_offset,
+            links: self.visible_links(display_offset),
+            #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+            images: self.visible_images(display_offset),
+        }
+    }
+
+    fn visible_links(&mut self, display_offset: usize) -> Vec<RenderableLink> {
+        let top = -(display_offset as i32);
+        let start = Point::new(Line(top), Column(0));
+        let end = Point::new(
+            Line(top + self.term.screen_lines() as i32 - 1),
+            self.term.last_column(),
+        );
+
+        RegexIter::new(
+            start,
+            end,
+            Direction::Right,
+            &self.term,
+            &mut self.url_regex,
+        )
+        .map(|url| RenderableLink {
+            range: grid_range(&url),
+            uri: self
+                .term
+                .bounds_to_string(*url.start(), *url.end())
+                .chars()
+                .filter(|c| !is_marker(*c))
+                .collect(),
+        })
+        .collect()
     }
 }
 
-#[derive(Clone)]
-struct EventProxy;
+fn grid_range(search_match: &Match) -> GridRange {
+    GridRange {
+        start_line: search_match.start().line.0,
+        start_column: search_match.start().column.0,
+        end_line: search_match.end().line.0,
+        end_column: search_match.end().column.0,
+    }
+}
+
+fn escape_regex(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+// Serializes spawns, so terminals started at the same time don't restore
+// each other's variables.
+static SPAWN_ENV_LOCK: StdMutex<()> = StdMutex::new(());
+
+// alacritty spawns the child with the environment of this process and has no
+// option for extra variables, so they are set here while it's spawned and
+// restored afterwards. Other threads reading the environment in that window
+// can still see them, which spawns through this type can't prevent.
+struct ScopedEnv {
+    previous: Vec<(String, Option<OsString>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl ScopedEnv {
+    fn set(env: &HashMap<String, String>) -> Self {
+        let lock = SPAWN_ENV_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let is_valid = |key: &str, value: &str| {
+            !key.is_empty()
+                && !key.contains(['=', '\0'])
+                && !value.contains('\0')
+        };
+        let previous = env
+            .iter()
+            .filter(|(key, value)| is_valid(key, value))
+            .map(|(key, value)| {
+                let previous = std::env::var_os(key);
+                std::env::set_var(key, value);
+                (key.clone(), previous)
+            })
+            .collect();
+
+        Self {
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+impl Drop for ScopedEnv {
+    fn drop(&mut self) {
+        for (key, value) in self.previous.drain(..) {
+            match value {
+                Some(value) => std::env::set_var(key, value),
+                None => std::env::remove_var(key),
+            }
+        }
+    }
+}
+
+fn wait_pid(pid: u32) -> Option<i32> {
+    let mut status = 0;
+    let res = unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) };
+    if res == pid as libc::pid_t && libc::WIFEXITED(status) {
+        Some(libc::WEXITSTATUS(status))
+    } else {
+        None
+    }
+}
+
+fn is_marker(c: char) -> bool {
+    if c == BLINK_MARKER {
+        return true;
+    }
+
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    if graphics::marker_id(c).is_some() {
+        return true;
+    }
+
+    prompt::is_marker(c)
+}
+
+#[cfg(target_os = "linux")]
+fn process_name(pid: u32) -> Option<String> {
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(name.trim_end().to_string())
+}
 
-impl EventProxy {}
+#[cfg(target_os = "macos")]
+fn process_name(pid: u32) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buf = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+    let len = unsafe {
+        libc::proc_pidpath(
+            pid as libc::c_int,
+            buf.as_mut_ptr().cast(),
+            buf.len() as u32,
+        )
+    };
+    if len <= 0 {
+        return None;
+    }
+
+    let path =
+        std::path::Path::new(std::ffi::OsStr::from_bytes(&buf[..len as usize]));
+    Some(path.file_name()?.to_string_lossy().into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_name(_pid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn process_directory(pid: u32) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_directory(_pid: u32) -> Option<PathBuf> {
+    None
+}
+
+// Checks without reaping, so the exit code is still there for `wait_pid`.
+fn has_exited(pid: u32) -> bool {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let res = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+
+    // Fails once the child has been reaped.
+    res != 0 || unsafe { info.si_pid() } != 0
+}
+
+fn signal_process_group(pid: u32, signal: i32) -> io::Result<()> {
+    // The shell is spawned as a session leader, so its pid is also the id
+    // of its own process group.
+    let res = unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+    if res == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[derive(Clone)]
+struct EventProxy {
+    sender: UnboundedSender<TerminalEvent>,
+}
 
 impl EventListener for EventProxy {
-    fn send_event(&self, _: alacritty_terminal::event::Event) {}
+    fn send_event(&self, event: Event) {
+        if let Some(event) = TerminalEvent::from_alacritty(event) {
+            let _ = self.sender.send(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alacritty_terminal::vte::ansi::{NamedColor, Rgb};
+    use std::time::Duration;
+
+    fn headless() -> Pty {
+        Pty::new_headless(0, BackendSettings::default())
+    }
+
+    fn pipe_reader() -> (PtyReader, File) {
+        use std::os::unix::io::FromRawFd;
+
+        let mut fds = [0; 2];
+        unsafe {
+            assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+            let flags = libc::fcntl(fds[0], libc::F_GETFL);
+            libc::fcntl(fds[0], libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        let (file, writer) =
+            unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+        (
+            PtyReader {
+                file,
+                fd: None,
+                pid: 0,
+            },
+            writer,
+        )
+    }
+
+    // alacritty_terminal hands the pty slave to the child as three owned
+    // stdio handles of the same fd, which the io safety checks of debug
+    // builds abort on, so this only runs with `--release`.
+    #[tokio::test]
+    async fn multibyte_input_round_trips() {
+        let settings = BackendSettings {
+            shell: String::from("/bin/cat"),
+            ..BackendSettings::default()
+        };
+        let mut pty = Pty::new(0, settings).unwrap();
+        let mut reader = pty.reader().unwrap();
+        pty.write_to_pty('€').unwrap();
+
+        let mut output = Vec::new();
+        let echoed = tokio::time::timeout(Duration::from_secs(5), async {
+            while !String::from_utf8_lossy(&output).contains('€') {
+                output.extend(Pty::read(&mut reader).await.unwrap());
+            }
+        });
+        assert!(echoed.await.is_ok(), "no echo in {:?}", output);
+        pty.kill().unwrap();
+    }
+
+    #[tokio::test]
+    async fn env_is_set_on_the_child_only() {
+        let settings = BackendSettings {
+            shell: String::from("/bin/sh"),
+            shell_args: vec![
+                String::from("-c"),
+                String::from("echo \"$0:$ICED_TERM_TEST\"; exec cat"),
+            ],
+            env: HashMap::from([(
+                String::from("ICED_TERM_TEST"),
+                String::from("value"),
+            )]),
+            ..BackendSettings::default()
+        };
+        let pty = Pty::new(0, settings).unwrap();
+        let mut reader = pty.reader().unwrap();
+
+        let mut output = Vec::new();
+        let printed = tokio::time::timeout(Duration::from_secs(5), async {
+            while !String::from_utf8_lossy(&output).contains('\n') {
+                output.extend(Pty::read(&mut reader).await.unwrap());
+            }
+        });
+        assert!(printed.await.is_ok(), "no output in {:?}", output);
+        assert!(String::from_utf8_lossy(&output).starts_with("/bin/sh:value"));
+        assert_eq!(std::env::var_os("ICED_TERM_TEST"), None);
+        pty.kill().unwrap();
+    }
+
+    #[tokio::test]
+    async fn kill_reaches_foreground_job() {
+        let settings = BackendSettings {
+            shell: String::from("/bin/sh"),
+            // The job ignores the hangup sent when the session leader
+            // exits, so only a signal sent to its own group stops it.
+            shell_args: vec![
+                String::from("-c"),
+                String::from("set -m; trap '' HUP; sleep 30"),
+            ],
+            ..BackendSettings::default()
+        };
+        let pty = Pty::new(0, settings).unwrap();
+        let job = async {
+            loop {
+                match pty.foreground_pgid() {
+                    Some(pgid) if Some(pgid) != pty.child_pid() => return pgid,
+                    _ => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        };
+        let job = tokio::time::timeout(Duration::from_secs(5), job).await;
+        let job = job.expect("sleep never became the foreground job");
+
+        pty.kill().unwrap();
+        let is_dead = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", job))
+                .map_or(true, |stat| stat.contains(") Z "))
+        };
+        let died = tokio::time::timeout(Duration::from_secs(5), async {
+            while !is_dead() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        assert!(died.await.is_ok(), "foreground job {} survived", job);
+    }
+
+    #[tokio::test]
+    async fn read_returns_only_bytes_read() {
+        let (mut reader, mut writer) = pipe_reader();
+        writer.write_all(b"hello").unwrap();
+
+        let data = Pty::read(&mut reader).await.unwrap();
+        assert_eq!(data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn read_reports_eof() {
+        let (mut reader, writer) = pipe_reader();
+        drop(writer);
+
+        match Pty::read(&mut reader).await {
+            Err(Error::Read(err)) => {
+                assert_eq!(err.kind(), ErrorKind::UnexpectedEof)
+            },
+            other => panic!("expected eof, got {:?}", other.map(|d| d.len())),
+        }
+    }
+
+    #[test]
+    fn bel_sends_bell_event() {
+        let mut pty = headless();
+        let events = pty.events();
+        pty.update(b"\x07".to_vec());
+
+        let event = events.try_lock().unwrap().try_recv();
+        assert!(matches!(event, Ok(TerminalEvent::Bell)));
+    }
+
+    #[test]
+    fn color_query_requests_reply() {
+        let mut pty = headless();
+        let events = pty.events();
+        pty.update(b"\x1b]11;?\x07".to_vec());
+
+        let event = events.try_lock().unwrap().try_recv();
+        let Ok(TerminalEvent::ColorRequest(color, formatter)) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(color, DynamicColor::Background);
+        assert_eq!(
+            formatter.format(Rgb {
+                r: 0x12,
+                g: 0x34,
+                b: 0x56
+            }),
+            "\x1b]11;rgb:1212/3434/5656\x07"
+        );
+    }
+
+    #[test]
+    fn pixel_size_query_uses_cell_size() {
+        let mut pty = headless();
+        let events = pty.events();
+        pty.resize(10, 40, 8.0, 16.0);
+        pty.update(b"\x1b[14t".to_vec());
+
+        let event = events.try_lock().unwrap().try_recv();
+        let Ok(TerminalEvent::TextAreaSizeRequest(formatter)) = event else {
+            panic!("unexpected event {:?}", event);
+        };
+        assert_eq!(formatter.format(pty.window_size()), "\x1b[4;160;320t");
+    }
+
+    #[test]
+    fn resize_reflows_long_lines() {
+        let mut pty = headless();
+        let line: String = ('a'..='z').cycle().take(70).collect();
+        pty.update(format!("$ {}", line).into_bytes());
+
+        pty.resize(10, 20, 8.0, 16.0);
+        let wrapped = pty.grid_text();
+        assert!(wrapped.iter().all(|row| row.chars().count() <= 20));
+        assert_eq!(wrapped.concat(), format!("$ {}", line));
+
+        pty.resize(10, 80, 8.0, 16.0);
+        let unwrapped = pty.grid_text();
+        assert!(unwrapped.contains(&format!("$ {}", line)));
+    }
+
+    #[test]
+    fn underline_color_reaches_cells() {
+        use ansi::Color::Indexed;
+
+        let mut pty = headless();
+        pty.update(b"\x1b[4:3m\x1b[58;5;196mX\x1b[59mY".to_vec());
+        let content = pty.cells();
+        let cell = |c| content.cells.iter().find(|cell| cell.content == c);
+
+        let curly = cell('X').unwrap();
+        assert!(curly.flags.contains(cell::Flags::UNDERCURL));
+        assert_eq!(curly.underline_color, Some(Indexed(196)));
+        let palette = crate::ColorPalette::default();
+        let color = crate::font::get_color(Indexed(196), &palette);
+        assert_eq!(color, iced::Color::from_rgb8(255, 0, 0));
+        assert_eq!(cell('Y').unwrap().underline_color, None);
+    }
+
+    #[test]
+    fn reset_restores_default_attributes() {
+        let mut pty = headless();
+        let lines = "line\r\n".repeat(100);
+        pty.update(format!("\x1b[1;4;31;44m{}", lines).into_bytes());
+
+        pty.reset();
+        pty.update(b"X".to_vec());
+        let content = pty.cells();
+        let cell = content.cells.iter().find(|cell| cell.content == 'X');
+        let cell = cell.unwrap();
+
+        assert_eq!(cell.fg, ansi::Color::Named(NamedColor::Foreground));
+        assert_eq!(cell.bg, ansi::Color::Named(NamedColor::Background));
+        assert!(!cell
+            .flags
+            .intersects(cell::Flags::BOLD | cell::Flags::UNDERLINE));
+        assert_eq!(
+            pty.scroll_state().total_lines,
+            pty.scroll_state().visible_lines
+        );
+        assert_eq!(pty.grid_text().concat(), "X");
+    }
+
+    #[test]
+    fn blink_marker_coexists_with_prompt_markers() {
+        let mut pty = headless();
+        pty.update(b"\x1b]133;A\x07\x1b[5mA\x1b]133;B\x07B\x1b[25mC".to_vec());
+        let content = pty.cells();
+        let cell = |c| content.cells.iter().find(|cell| cell.content == c);
+
+        assert!(cell('A').unwrap().is_blinking());
+        assert!(cell('B').unwrap().is_blinking());
+        assert!(!cell('C').unwrap().is_blinking());
+        let regions = pty.command_regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].command_line, Some(0));
+    }
+
+    #[test]
+    fn link_uri_skips_prompt_markers() {
+        let mut pty = headless();
+        pty.update(
+            b"\x1b]133;A\x07$ \x1b]133;B\x07curl https://example.com/a"
+                .to_vec(),
+        );
+
+        let content = pty.cells();
+        let uris: Vec<&str> =
+            content.links.iter().map(|link| link.uri.as_str()).collect();
+        assert_eq!(uris, ["https://example.com/a"]);
+    }
+
+    #[test]
+    fn prompt_end_drops_template_extra() {
+        let mut pty = headless();
+        pty.update(b"\x1b]133;A\x07$ \x1b]133;B\x07ls\x1b]133;C\x07".to_vec());
+
+        assert!(pty.term.grid().cursor.template.extra.is_none());
+    }
 }
@@ -1,5 +1,11 @@
+use alacritty_terminal::term::cell;
 use alacritty_terminal::vte::ansi;
 
+// alacritty parses SGR 5 and 6 but keeps no blink attribute, so blinking
+// text is tagged with a private use zero-width character kept in the cursor
+// template, the same way prompts and images are tracked.
+pub const BLINK_MARKER: char = '\u{107ffe}';
+
 #[derive(Clone, Debug)]
 pub struct RenderableCell {
     pub column: usize,
@@ -8,4 +14,19 @@ pub struct RenderableCell {
     pub display_offset: usize,
     pub fg: ansi::Color,
     pub bg: ansi::Color,
+    pub flags: cell::Flags,
+    pub underline_color: Option<ansi::Color>,
+    pub blinking: bool,
+    pub hyperlink: Option<String>,
+    pub is_selected: bool,
+}
+
+impl RenderableCell {
+    pub fn is_blinking(&self) -> bool {
+        self.blinking
+    }
+
+    pub fn is_concealed(&self) -> bool {
+        self.flags.contains(cell::Flags::HIDDEN) && !self.is_selected
+    }
 }
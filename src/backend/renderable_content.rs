@@ -0,0 +1,68 @@
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+use crate::backend::RenderableImage;
+use crate::backend::{GridRange, RenderableCell};
+use alacritty_terminal::term::cell;
+use alacritty_terminal::vte::ansi::{self, NamedColor};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Underline,
+    Bar,
+    Hidden,
+}
+
+impl From<ansi::CursorShape> for CursorShape {
+    fn from(shape: ansi::CursorShape) -> Self {
+        match shape {
+            ansi::CursorShape::Block | ansi::CursorShape::HollowBlock => {
+                CursorShape::Block
+            },
+            ansi::CursorShape::Underline => CursorShape::Underline,
+            ansi::CursorShape::Beam => CursorShape::Bar,
+            ansi::CursorShape::Hidden => CursorShape::Hidden,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderableCursor {
+    pub shape: CursorShape,
+    pub column: usize,
+    pub line: i32,
+    pub content: char,
+    pub fg: ansi::Color,
+    pub bg: ansi::Color,
+    pub flags: cell::Flags,
+}
+
+impl Default for RenderableCursor {
+    fn default() -> Self {
+        Self {
+            shape: CursorShape::default(),
+            column: 0,
+            line: 0,
+            content: ' ',
+            fg: ansi::Color::Named(NamedColor::Foreground),
+            bg: ansi::Color::Named(NamedColor::Background),
+            flags: cell::Flags::empty(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderableLink {
+    pub range: GridRange,
+    pub uri: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RenderableContent {
+    pub cells: Vec<RenderableCell>,
+    pub cursor: RenderableCursor,
+    pub display_offset: usize,
+    pub links: Vec<RenderableLink>,
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    pub images: Vec<RenderableImage>,
+}
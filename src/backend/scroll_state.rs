@@ -0,0 +1,11 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScrollState {
+    /// Lines scrolled back from the bottom of the history.
+    pub display_offset: usize,
+    /// Scrollback plus the visible screen.
+    pub total_lines: usize,
+    pub visible_lines: usize,
+    /// Output arrived while scrolled back, cleared once the view is back
+    /// at the bottom.
+    pub has_new_output_below: bool,
+}
@@ -1,20 +1,158 @@
-const DEFAULT_SHELL: &str = "/bin/bash";
+pub use alacritty_terminal::term::Osc52;
+use alacritty_terminal::term::SEMANTIC_ESCAPE_CHARS;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const FALLBACK_SHELL: &str = "/bin/sh";
 const DEFAULT_COLS_NUM: u16 = 50;
 const DEFAULT_ROWS_NUM: u16 = 50;
+const DEFAULT_SCROLLBACK_LINES: usize = 10000;
+const DEFAULT_TERM: &str = "xterm-256color";
 
 #[derive(Debug, Clone)]
 pub struct BackendSettings {
+    /// Program started in the terminal. Defaults to the login shell of the
+    /// user, from `$SHELL` or the passwd database, and to `/bin/sh` if
+    /// neither has one. There is no Windows default, since the crate only
+    /// builds on unix.
     pub shell: String,
+    pub shell_args: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+    /// Extra variables for the child process. alacritty itself only sets
+    /// `USER`, `HOME`, `WINDOWID` and `ALACRITTY_WINDOW_ID`, everything
+    /// else is inherited from the current process. Names that are empty or
+    /// contain `=` or NUL, and values containing NUL, are skipped.
+    ///
+    /// alacritty can only pass its own environment to the child, so these
+    /// are set on the current process while the child is spawned. Threads
+    /// of the application reading the environment at that moment may see
+    /// them.
+    pub env: HashMap<String, String>,
     pub cols: u16,
     pub rows: u16,
+    pub scrollback_lines: usize,
+    pub semantic_escape_chars: String,
+    /// Access to the system clipboard through OSC 52. Disabled by default,
+    /// since any program in the terminal, including one on a remote host,
+    /// could otherwise read or overwrite the clipboard.
+    pub osc52: Osc52,
 }
 
 impl Default for BackendSettings {
     fn default() -> Self {
         Self {
-            shell: DEFAULT_SHELL.to_string(),
+            shell: default_shell(),
+            shell_args: Vec::new(),
+            working_directory: None,
+            env: HashMap::from([(
+                String::from("TERM"),
+                String::from(DEFAULT_TERM),
+            )]),
             cols: DEFAULT_COLS_NUM,
             rows: DEFAULT_ROWS_NUM,
+            scrollback_lines: DEFAULT_SCROLLBACK_LINES,
+            semantic_escape_chars: SEMANTIC_ESCAPE_CHARS.to_string(),
+            osc52: Osc52::Disabled,
         }
     }
 }
+
+impl BackendSettings {
+    pub fn builder() -> BackendSettingsBuilder {
+        BackendSettingsBuilder::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackendSettingsBuilder {
+    settings: BackendSettings,
+}
+
+impl BackendSettingsBuilder {
+    pub fn shell(mut self, shell: impl Into<String>) -> Self {
+        self.settings.shell = shell.into();
+        self
+    }
+
+    pub fn shell_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.settings.shell_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn working_directory(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings.working_directory = Some(path.into());
+        self
+    }
+
+    pub fn env(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.settings.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn cols(mut self, cols: u16) -> Self {
+        self.settings.cols = cols;
+        self
+    }
+
+    pub fn rows(mut self, rows: u16) -> Self {
+        self.settings.rows = rows;
+        self
+    }
+
+    pub fn scrollback(mut self, lines: usize) -> Self {
+        self.settings.scrollback_lines = lines;
+        self
+    }
+
+    pub fn semantic_escape_chars(mut self, chars: impl Into<String>) -> Self {
+        self.settings.semantic_escape_chars = chars.into();
+        self
+    }
+
+    pub fn osc52(mut self, osc52: Osc52) -> Self {
+        self.settings.osc52 = osc52;
+        self
+    }
+
+    pub fn build(self) -> BackendSettings {
+        self.settings
+    }
+}
+
+fn default_shell() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .or_else(passwd_shell)
+        .unwrap_or_else(|| FALLBACK_SHELL.to_string())
+}
+
+fn passwd_shell() -> Option<String> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let res = unsafe {
+        libc::getpwuid_r(
+            libc::getuid(),
+            &mut passwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if res != 0 || result.is_null() || passwd.pw_shell.is_null() {
+        return None;
+    }
+
+    let shell = unsafe { std::ffi::CStr::from_ptr(passwd.pw_shell) };
+    let shell = shell.to_str().ok()?;
+    (!shell.is_empty()).then(|| shell.to_string())
+}
@@ -0,0 +1,293 @@
+use crate::backend::graphics::{ImageData, InlineImage};
+
+const MAX_DIMENSION: usize = 4096;
+const SIXEL_HEIGHT: usize = 6;
+const PALETTE_SIZE: usize = 256;
+// VT340 default colors, in percent.
+const DEFAULT_PALETTE: [(u16, u16, u16); 16] = [
+    (0, 0, 0),
+    (20, 20, 80),
+    (80, 13, 13),
+    (20, 80, 20),
+    (80, 20, 80),
+    (20, 80, 80),
+    (80, 80, 20),
+    (53, 53, 53),
+    (26, 26, 26),
+    (33, 33, 60),
+    (60, 26, 26),
+    (33, 60, 33),
+    (60, 33, 60),
+    (33, 60, 60),
+    (60, 60, 33),
+    (80, 80, 80),
+];
+
+pub fn decode(data: &[u8]) -> Option<InlineImage> {
+    let mut decoder = Decoder::new();
+    let mut bytes = data.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            // The declared raster size is ignored, the image only covers
+            // the pixels that are drawn.
+            b'"' => {
+                parse_params(&mut bytes);
+            },
+            b'#' => {
+                let params = parse_params(&mut bytes);
+                match params[..] {
+                    [index, 1, h, l, s] => {
+                        decoder.define_color(index, hls_to_rgb(h, l, s))
+                    },
+                    [index, 2, r, g, b] => decoder.define_color(
+                        index,
+                        [percent(r), percent(g), percent(b)],
+                    ),
+                    [index, ..] => decoder.select_color(index),
+                    [] => {},
+                }
+            },
+            b'!' => {
+                let count = parse_params(&mut bytes).first().copied();
+                if let Some(sixel) = bytes.next() {
+                    decoder.put(sixel, count.unwrap_or(1).max(1) as usize);
+                }
+            },
+            b'$' => decoder.x = 0,
+            b'-' => {
+                decoder.x = 0;
+                decoder.y += SIXEL_HEIGHT;
+            },
+            _ => decoder.put(byte, 1),
+        }
+    }
+
+    decoder.finish()
+}
+
+struct Decoder {
+    palette: [[u8; 3]; PALETTE_SIZE],
+    color: usize,
+    x: usize,
+    y: usize,
+    // Extent of the drawn pixels.
+    width: usize,
+    height: usize,
+    // Size of `pixels`, grown in steps so a long line doesn't copy the
+    // buffer for every sixel.
+    stride: usize,
+    rows: usize,
+    pixels: Vec<u8>,
+}
+
+impl Decoder {
+    fn new() -> Self {
+        let mut palette = [[0; 3]; PALETTE_SIZE];
+        for (color, (r, g, b)) in palette.iter_mut().zip(DEFAULT_PALETTE) {
+            *color = [percent(r), percent(g), percent(b)];
+        }
+
+        Self {
+            palette,
+            color: 0,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            stride: 0,
+            rows: 0,
+            pixels: Vec::new(),
+        }
+    }
+
+    fn define_color(&mut self, index: u16, rgb: [u8; 3]) {
+        let index = usize::from(index) % PALETTE_SIZE;
+        self.palette[index] = rgb;
+        self.color = index;
+    }
+
+    fn select_color(&mut self, index: u16) {
+        self.color = usize::from(index) % PALETTE_SIZE;
+    }
+
+    fn put(&mut self, sixel: u8, count: usize) {
+        if !(0x3f..=0x7e).contains(&sixel) {
+            return;
+        }
+
+        let bits = sixel - 0x3f;
+        let end = (self.x + count).min(MAX_DIMENSION);
+        if bits != 0 && end > self.x && self.y + SIXEL_HEIGHT <= MAX_DIMENSION {
+            self.grow(end, self.y + SIXEL_HEIGHT);
+            let [r, g, b] = self.palette[self.color];
+            for row in 0..SIXEL_HEIGHT {
+                if bits & (1 << row) == 0 {
+                    continue;
+                }
+
+                let offset = (self.y + row) * self.stride;
+                for x in self.x..end {
+                    let i = (offset + x) * 4;
+                    self.pixels[i..i + 4].copy_from_slice(&[r, g, b, 0xff]);
+                }
+            }
+        }
+
+        self.x = end;
+    }
+
+    fn grow(&mut self, width: usize, height: usize) {
+        self.width = self.width.max(width);
+        self.height = self.height.max(height);
+        if self.width <= self.stride && self.height <= self.rows {
+            return;
+        }
+
+        let stride = self.stride.max(self.width.next_power_of_two());
+        let rows = self.rows.max(self.height.next_power_of_two());
+        let (stride, rows) =
+            (stride.min(MAX_DIMENSION), rows.min(MAX_DIMENSION));
+        let mut pixels = vec![0; stride * rows * 4];
+        for y in 0..self.rows {
+            let src = y * self.stride * 4;
+            let dst = y * stride * 4;
+            pixels[dst..dst + self.stride * 4]
+                .copy_from_slice(&self.pixels[src..src + self.stride * 4]);
+        }
+
+        self.pixels = pixels;
+        self.stride = stride;
+        self.rows = rows;
+    }
+
+    fn finish(self) -> Option<InlineImage> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+
+        let row_bytes = self.width * 4;
+        let pixels: Vec<u8> = (0..self.height)
+            .flat_map(|y| {
+                let start = y * self.stride * 4;
+                self.pixels[start..start + row_bytes].iter().copied()
+            })
+            .collect();
+        Some(InlineImage {
+            width: self.width as u32,
+            height: self.height as u32,
+            data: ImageData::Rgba(pixels.into()),
+        })
+    }
+}
+
+fn parse_params(
+    bytes: &mut std::iter::Peekable<impl Iterator<Item = u8>>,
+) -> Vec<u16> {
+    let mut params = Vec::new();
+    let mut value: Option<u16> = None;
+    while let Some(&byte) = bytes.peek() {
+        match byte {
+            b'0'..=b'9' => {
+                let digit = u16::from(byte - b'0');
+                value = Some(
+                    value.unwrap_or(0).saturating_mul(10).saturating_add(digit),
+                );
+            },
+            b';' => params.push(value.take().unwrap_or(0)),
+            _ => break,
+        }
+        bytes.next();
+    }
+    if let Some(value) = value {
+        params.push(value);
+    }
+
+    params
+}
+
+fn percent(value: u16) -> u8 {
+    (u32::from(value.min(100)) * 255 / 100) as u8
+}
+
+fn hls_to_rgb(hue: u16, lightness: u16, saturation: u16) -> [u8; 3] {
+    // Sixel hues start at blue, HSL hues start at red.
+    let hue = (f32::from(hue % 360) + 240.0) % 360.0 / 360.0;
+    let lightness = f32::from(lightness.min(100)) / 100.0;
+    let saturation = f32::from(saturation.min(100)) / 100.0;
+    if saturation == 0.0 {
+        let gray = (lightness * 255.0) as u8;
+        return [gray, gray, gray];
+    }
+
+    let q = if lightness < 0.5 {
+        lightness * (1.0 + saturation)
+    } else {
+        lightness + saturation - lightness * saturation
+    };
+    let p = 2.0 * lightness - q;
+    let channel = |t: f32| {
+        let t = t.rem_euclid(1.0);
+        let value = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (value * 255.0) as u8
+    };
+
+    [
+        channel(hue + 1.0 / 3.0),
+        channel(hue),
+        channel(hue - 1.0 / 3.0),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_raster_size_is_not_allocated() {
+        let image = decode(b"\"1;1;4096;4096#1~").unwrap();
+
+        assert_eq!((image.width, image.height), (1, 6));
+        assert_eq!(image.byte_len(), 6 * 4);
+    }
+
+    fn pixel(image: &InlineImage, x: usize, y: usize) -> [u8; 4] {
+        let ImageData::Rgba(data) = &image.data else {
+            panic!("sixel images are decoded to rgba");
+        };
+        let i = (y * image.width as usize + x) * 4;
+        data[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn decodes_palette_repeat_and_line_control() {
+        let image = decode(b"#1;2;100;0;0!2~$#2;2;0;100;0@-#1~").unwrap();
+
+        assert_eq!((image.width, image.height), (2, 12));
+        assert_eq!(pixel(&image, 0, 0), [0, 0xff, 0, 0xff]);
+        assert_eq!(pixel(&image, 1, 0), [0xff, 0, 0, 0xff]);
+        assert_eq!(pixel(&image, 0, 5), [0xff, 0, 0, 0xff]);
+        assert_eq!(pixel(&image, 0, 6), [0xff, 0, 0, 0xff]);
+        assert_eq!(pixel(&image, 1, 6), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn drawing_is_clamped_to_max_dimension() {
+        let mut data = b"#1!5000~".to_vec();
+        for _ in 0..MAX_DIMENSION / SIXEL_HEIGHT + 1 {
+            data.extend_from_slice(b"-~");
+        }
+        let image = decode(&data).unwrap();
+
+        assert_eq!(image.width as usize, MAX_DIMENSION);
+        let rows = MAX_DIMENSION / SIXEL_HEIGHT * SIXEL_HEIGHT;
+        assert_eq!(image.height as usize, rows);
+    }
+}
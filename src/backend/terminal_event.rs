@@ -0,0 +1,128 @@
+use alacritty_terminal::event::{Event, WindowSize};
+use alacritty_terminal::vte::ansi::{NamedColor, Rgb};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum TerminalEvent {
+    Title(String),
+    TitleReset,
+    Bell,
+    ClipboardStore(String),
+    ClipboardLoad(ClipboardFormatter),
+    PtyWrite(String),
+    WorkingDirectoryChanged(PathBuf),
+    ColorChanged(DynamicColor, Option<Rgb>),
+    ColorRequest(DynamicColor, ColorFormatter),
+    TextAreaSizeRequest(TextAreaSizeFormatter),
+}
+
+/// A color the running program can override with an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DynamicColor {
+    /// Palette entry set by OSC 4 and reset by OSC 104.
+    Indexed(u8),
+    /// Set by OSC 10 and reset by OSC 110.
+    Foreground,
+    /// Set by OSC 11 and reset by OSC 111.
+    Background,
+    /// Set by OSC 12 and reset by OSC 112.
+    Cursor,
+}
+
+impl DynamicColor {
+    pub(crate) fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0..=255 => Some(DynamicColor::Indexed(index as u8)),
+            i if i == NamedColor::Foreground as usize => {
+                Some(DynamicColor::Foreground)
+            },
+            i if i == NamedColor::Background as usize => {
+                Some(DynamicColor::Background)
+            },
+            i if i == NamedColor::Cursor as usize => Some(DynamicColor::Cursor),
+            _ => None,
+        }
+    }
+}
+
+impl TerminalEvent {
+    pub fn from_alacritty(event: Event) -> Option<Self> {
+        match event {
+            Event::Title(title) => Some(TerminalEvent::Title(title)),
+            Event::ResetTitle => Some(TerminalEvent::TitleReset),
+            Event::Bell => Some(TerminalEvent::Bell),
+            Event::ClipboardStore(_, text) => {
+                Some(TerminalEvent::ClipboardStore(text))
+            },
+            Event::ClipboardLoad(_, formatter) => Some(
+                TerminalEvent::ClipboardLoad(ClipboardFormatter(formatter)),
+            ),
+            Event::PtyWrite(text) => Some(TerminalEvent::PtyWrite(text)),
+            Event::ColorRequest(index, formatter) => {
+                DynamicColor::from_index(index).map(|color| {
+                    TerminalEvent::ColorRequest(
+                        color,
+                        ColorFormatter(formatter),
+                    )
+                })
+            },
+            Event::TextAreaSizeRequest(formatter) => {
+                Some(TerminalEvent::TextAreaSizeRequest(TextAreaSizeFormatter(
+                    formatter,
+                )))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ClipboardFormatter(Arc<dyn Fn(&str) -> String + Sync + Send>);
+
+impl ClipboardFormatter {
+    pub fn format(&self, text: &str) -> String {
+        (self.0)(text)
+    }
+}
+
+impl fmt::Debug for ClipboardFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ClipboardFormatter")
+    }
+}
+
+/// Builds the reply to an OSC 4, 10, 11 or 12 query from the color in use.
+#[derive(Clone)]
+pub struct ColorFormatter(Arc<dyn Fn(Rgb) -> String + Sync + Send>);
+
+impl ColorFormatter {
+    pub(crate) fn format(&self, rgb: Rgb) -> String {
+        (self.0)(rgb)
+    }
+}
+
+impl fmt::Debug for ColorFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ColorFormatter")
+    }
+}
+
+/// Builds the reply to a `CSI 14 t` query for the text area size in pixels.
+#[derive(Clone)]
+pub struct TextAreaSizeFormatter(
+    Arc<dyn Fn(WindowSize) -> String + Sync + Send>,
+);
+
+impl TextAreaSizeFormatter {
+    pub(crate) fn format(&self, size: WindowSize) -> String {
+        (self.0)(size)
+    }
+}
+
+impl fmt::Debug for TextAreaSizeFormatter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TextAreaSizeFormatter")
+    }
+}
@@ -0,0 +1,297 @@
+use iced::widget::canvas::{Frame, Path, Stroke};
+use iced::{Color, Point, Rectangle, Size};
+
+const LIGHT: u8 = 1;
+const HEAVY: u8 = 2;
+const UPPER_LEFT: u8 = 1;
+const UPPER_RIGHT: u8 = 2;
+const LOWER_LEFT: u8 = 4;
+const LOWER_RIGHT: u8 = 8;
+
+// Left, right, up and down arm weights for U+2500..=U+254B.
+#[rustfmt::skip]
+const LINES: [[u8; 4]; 0x4c] = [
+    [1, 1, 0, 0], [2, 2, 0, 0], [0, 0, 1, 1], [0, 0, 2, 2],
+    [1, 1, 0, 0], [2, 2, 0, 0], [0, 0, 1, 1], [0, 0, 2, 2],
+    [1, 1, 0, 0], [2, 2, 0, 0], [0, 0, 1, 1], [0, 0, 2, 2],
+    [0, 1, 0, 1], [0, 2, 0, 1], [0, 1, 0, 2], [0, 2, 0, 2],
+    [1, 0, 0, 1], [2, 0, 0, 1], [1, 0, 0, 2], [2, 0, 0, 2],
+    [0, 1, 1, 0], [0, 2, 1, 0], [0, 1, 2, 0], [0, 2, 2, 0],
+    [1, 0, 1, 0], [2, 0, 1, 0], [1, 0, 2, 0], [2, 0, 2, 0],
+    [0, 1, 1, 1], [0, 2, 1, 1], [0, 1, 2, 1], [0, 1, 1, 2],
+    [0, 1, 2, 2], [0, 2, 2, 1], [0, 2, 1, 2], [0, 2, 2, 2],
+    [1, 0, 1, 1], [2, 0, 1, 1], [1, 0, 2, 1], [1, 0, 1, 2],
+    [1, 0, 2, 2], [2, 0, 2, 1], [2, 0, 1, 2], [2, 0, 2, 2],
+    [1, 1, 0, 1], [2, 1, 0, 1], [1, 2, 0, 1], [2, 2, 0, 1],
+    [1, 1, 0, 2], [2, 1, 0, 2], [1, 2, 0, 2], [2, 2, 0, 2],
+    [1, 1, 1, 0], [2, 1, 1, 0], [1, 2, 1, 0], [2, 2, 1, 0],
+    [1, 1, 2, 0], [2, 1, 2, 0], [1, 2, 2, 0], [2, 2, 2, 0],
+    [1, 1, 1, 1], [2, 1, 1, 1], [1, 2, 1, 1], [2, 2, 1, 1],
+    [1, 1, 2, 1], [1, 1, 1, 2], [1, 1, 2, 2], [2, 1, 2, 1],
+    [1, 2, 2, 1], [2, 1, 1, 2], [1, 2, 1, 2], [2, 2, 2, 1],
+    [2, 2, 1, 2], [2, 1, 2, 2], [1, 2, 2, 2], [2, 2, 2, 2],
+];
+
+// Left, right, up and down arm weights for U+2574..=U+257F.
+#[rustfmt::skip]
+const HALF_LINES: [[u8; 4]; 12] = [
+    [1, 0, 0, 0], [0, 0, 1, 0], [0, 1, 0, 0], [0, 0, 0, 1],
+    [2, 0, 0, 0], [0, 0, 2, 0], [0, 2, 0, 0], [0, 0, 0, 2],
+    [1, 2, 0, 0], [0, 0, 1, 2], [2, 1, 0, 0], [0, 0, 2, 1],
+];
+
+/// Draws box-drawing and block element characters with primitives snapped
+/// to the pixel grid, so that neighbouring cells join without gaps. Returns
+/// `false` for characters that are left to the font.
+pub fn draw(
+    frame: &mut Frame,
+    c: char,
+    bounds: Rectangle,
+    color: Color,
+) -> bool {
+    let cell = Cell::new(bounds);
+    match c {
+        '\u{2504}'..='\u{250b}' => {
+            let dashes = if c < '\u{2508}' { 3 } else { 4 };
+            dashed(frame, &cell, LINES[c as usize - 0x2500], dashes, color);
+        },
+        '\u{2500}'..='\u{254b}' => {
+            lines(frame, &cell, LINES[c as usize - 0x2500], color)
+        },
+        '\u{254c}'..='\u{254f}' => {
+            let index = c as usize - 0x254c;
+            dashed(frame, &cell, LINES[index], 2, color);
+        },
+        '\u{256d}'..='\u{2570}' => arc(frame, &cell, c, color),
+        '\u{2571}'..='\u{2573}' => diagonal(frame, &cell, c, color),
+        '\u{2574}'..='\u{257f}' => {
+            lines(frame, &cell, HALF_LINES[c as usize - 0x2574], color)
+        },
+        '\u{2580}' => cell.fill(frame, 0.0, 0.0, 1.0, 0.5, color),
+        '\u{2581}'..='\u{2588}' => {
+            let height = (c as u32 - 0x2580) as f32 / 8.0;
+            cell.fill(frame, 0.0, 1.0 - height, 1.0, 1.0, color);
+        },
+        '\u{2589}'..='\u{258f}' => {
+            let width = (0x2590 - c as u32) as f32 / 8.0;
+            cell.fill(frame, 0.0, 0.0, width, 1.0, color);
+        },
+        '\u{2590}' => cell.fill(frame, 0.5, 0.0, 1.0, 1.0, color),
+        '\u{2591}'..='\u{2593}' => {
+            let alpha = (c as u32 - 0x2590) as f32 / 4.0;
+            let color = Color {
+                a: color.a * alpha,
+                ..color
+            };
+            cell.fill(frame, 0.0, 0.0, 1.0, 1.0, color);
+        },
+        '\u{2594}' => cell.fill(frame, 0.0, 0.0, 1.0, 0.125, color),
+        '\u{2595}' => cell.fill(frame, 0.875, 0.0, 1.0, 1.0, color),
+        '\u{2596}'..='\u{259f}' => {
+            let quadrants = match c {
+                '\u{2596}' => LOWER_LEFT,
+                '\u{2597}' => LOWER_RIGHT,
+                '\u{2598}' => UPPER_LEFT,
+                '\u{2599}' => UPPER_LEFT | LOWER_LEFT | LOWER_RIGHT,
+                '\u{259a}' => UPPER_LEFT | LOWER_RIGHT,
+                '\u{259b}' => UPPER_LEFT | UPPER_RIGHT | LOWER_LEFT,
+                '\u{259c}' => UPPER_LEFT | UPPER_RIGHT | LOWER_RIGHT,
+                '\u{259d}' => UPPER_RIGHT,
+                '\u{259e}' => UPPER_RIGHT | LOWER_LEFT,
+                _ => UPPER_RIGHT | LOWER_LEFT | LOWER_RIGHT,
+            };
+            quadrant(frame, &cell, quadrants, color);
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+struct Cell {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    light: f32,
+}
+
+impl Cell {
+    fn new(bounds: Rectangle) -> Self {
+        let left = bounds.x.round();
+        let top = bounds.y.round();
+        let right = (bounds.x + bounds.width).round().max(left + 1.0);
+        let bottom = (bounds.y + bounds.height).round().max(top + 1.0);
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+            light: (bounds.width / 8.0).round().max(1.0),
+        }
+    }
+
+    fn thickness(&self, weight: u8) -> f32 {
+        match weight {
+            HEAVY => self.light * 2.0,
+            _ => self.light,
+        }
+    }
+
+    // Start of a vertical stroke of the given weight centered in the cell.
+    fn column(&self, weight: u8) -> f32 {
+        let thickness = self.thickness(weight);
+        self.left + ((self.right - self.left - thickness) / 2.0).floor()
+    }
+
+    // Start of a horizontal stroke of the given weight centered in the cell.
+    fn row(&self, weight: u8) -> f32 {
+        let thickness = self.thickness(weight);
+        self.top + ((self.bottom - self.top - thickness) / 2.0).floor()
+    }
+
+    fn x(&self, fraction: f32) -> f32 {
+        self.left + ((self.right - self.left) * fraction).round()
+    }
+
+    fn y(&self, fraction: f32) -> f32 {
+        self.top + ((self.bottom - self.top) * fraction).round()
+    }
+
+    fn fill(
+        &self,
+        frame: &mut Frame,
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        color: Color,
+    ) {
+        let (left, top) = (self.x(x0), self.y(y0));
+        let (right, bottom) = (self.x(x1), self.y(y1));
+        rectangle(frame, left, top, right, bottom, color);
+    }
+}
+
+fn rectangle(
+    frame: &mut Frame,
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    color: Color,
+) {
+    if right > left && bottom > top {
+        frame.fill_rectangle(
+            Point::new(left, top),
+            Size::new(right - left, bottom - top),
+            color,
+        );
+    }
+}
+
+fn lines(frame: &mut Frame, cell: &Cell, arms: [u8; 4], color: Color) {
+    let [left, right, up, down] = arms;
+    // Horizontal arms reach across the vertical stroke and the other way
+    // round, so corners and junctions are fully covered.
+    let vertical = up.max(down).max(1);
+    let horizontal = left.max(right).max(1);
+    let column = cell.column(vertical);
+    let row = cell.row(horizontal);
+    let column_end = column + cell.thickness(vertical);
+    let row_end = row + cell.thickness(horizontal);
+
+    if left != 0 {
+        let y = cell.row(left);
+        let bottom = y + cell.thickness(left);
+        rectangle(frame, cell.left, y, column_end, bottom, color);
+    }
+    if right != 0 {
+        let y = cell.row(right);
+        let bottom = y + cell.thickness(right);
+        rectangle(frame, column, y, cell.right, bottom, color);
+    }
+    if up != 0 {
+        let x = cell.column(up);
+        let right = x + cell.thickness(up);
+        rectangle(frame, x, cell.top, right, row_end, color);
+    }
+    if down != 0 {
+        let x = cell.column(down);
+        let right = x + cell.thickness(down);
+        rectangle(frame, x, row, right, cell.bottom, color);
+    }
+}
+
+fn dashed(
+    frame: &mut Frame,
+    cell: &Cell,
+    arms: [u8; 4],
+    dashes: u8,
+    color: Color,
+) {
+    let [left, _, up, _] = arms;
+    for dash in 0..dashes {
+        let start = f32::from(dash) / f32::from(dashes);
+        let end = (f32::from(dash) + 0.5) / f32::from(dashes);
+        if left != 0 {
+            let y = cell.row(left);
+            let bottom = y + cell.thickness(left);
+            rectangle(frame, cell.x(start), y, cell.x(end), bottom, color);
+        } else {
+            let x = cell.column(up);
+            let right = x + cell.thickness(up);
+            rectangle(frame, x, cell.y(start), right, cell.y(end), color);
+        }
+    }
+}
+
+fn arc(frame: &mut Frame, cell: &Cell, c: char, color: Color) {
+    let center = Point::new(
+        cell.column(LIGHT) + cell.light / 2.0,
+        cell.row(LIGHT) + cell.light / 2.0,
+    );
+    let (x, y) = match c {
+        '\u{256d}' => (cell.right, cell.bottom),
+        '\u{256e}' => (cell.left, cell.bottom),
+        '\u{256f}' => (cell.left, cell.top),
+        _ => (cell.right, cell.top),
+    };
+
+    let path = Path::new(|path| {
+        path.move_to(Point::new(x, center.y));
+        path.quadratic_curve_to(center, Point::new(center.x, y));
+    });
+    stroke(frame, &path, cell.light, color);
+}
+
+fn diagonal(frame: &mut Frame, cell: &Cell, c: char, color: Color) {
+    let path = Path::new(|path| {
+        if c != '\u{2572}' {
+            path.move_to(Point::new(cell.right, cell.top));
+            path.line_to(Point::new(cell.left, cell.bottom));
+        }
+        if c != '\u{2571}' {
+            path.move_to(Point::new(cell.left, cell.top));
+            path.line_to(Point::new(cell.right, cell.bottom));
+        }
+    });
+    stroke(frame, &path, cell.light, color);
+}
+
+fn stroke(frame: &mut Frame, path: &Path, width: f32, color: Color) {
+    frame.stroke(path, Stroke::default().with_width(width).with_color(color));
+}
+
+fn quadrant(frame: &mut Frame, cell: &Cell, quadrants: u8, color: Color) {
+    for (quadrant, x, y) in [
+        (UPPER_LEFT, 0.0, 0.0),
+        (UPPER_RIGHT, 0.5, 0.0),
+        (LOWER_LEFT, 0.0, 0.5),
+        (LOWER_RIGHT, 0.5, 0.5),
+    ] {
+        if quadrants & quadrant != 0 {
+            cell.fill(frame, x, y, x + 0.5, y + 0.5, color);
+        }
+    }
+}
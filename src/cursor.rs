@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+const DEFAULT_BLINK_INTERVAL_MS: u64 = 530;
+
+#[derive(Debug, Clone)]
+pub struct CursorSettings {
+    pub visible: bool,
+    pub blinking: bool,
+    pub blink_interval: Duration,
+}
+
+impl Default for CursorSettings {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            blinking: true,
+            blink_interval: Duration::from_millis(DEFAULT_BLINK_INTERVAL_MS),
+        }
+    }
+}
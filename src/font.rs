@@ -1,24 +1,136 @@
-use alacritty_terminal::vte::ansi::NamedColor;
+use crate::theme::ColorPalette;
+use alacritty_terminal::vte::ansi::{self, NamedColor};
 use iced::advanced::text;
+use iced::font::{Family, Stretch, Weight};
+use iced::widget::text::Shaping;
 use iced::Color;
-use iced::{Font, Size, Theme};
+use iced::{Font, Pixels, Size, Theme};
 use iced_graphics::renderer::Renderer;
 use iced_tiny_skia::{Backend, Settings};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const DIM_FACTOR: f32 = 0.66;
+const MIN_CONTRAST_RATIO: f32 = 3.0;
+const MIN_FONT_SIZE: f32 = 6.0;
+const MAX_FONT_SIZE: f32 = 72.0;
+const DEFAULT_LINE_HEIGHT: f32 = 1.2;
+const ANSI_NAMED_COLORS: [NamedColor; 16] = [
+    NamedColor::Black,
+    NamedColor::Red,
+    NamedColor::Green,
+    NamedColor::Yellow,
+    NamedColor::Blue,
+    NamedColor::Magenta,
+    NamedColor::Cyan,
+    NamedColor::White,
+    NamedColor::BrightBlack,
+    NamedColor::BrightRed,
+    NamedColor::BrightGreen,
+    NamedColor::BrightYellow,
+    NamedColor::BrightBlue,
+    NamedColor::BrightMagenta,
+    NamedColor::BrightCyan,
+    NamedColor::BrightWhite,
+];
 
 #[derive(Clone)]
 pub struct FontSettings {
     pub size: f32,
+    pub font_type: Font,
+    pub line_height: f32,
+    pub letter_spacing: f32,
+    /// Font for bold cells. Defaults to a bold weight of `font_type`.
+    pub bold_font_type: Option<Font>,
+    /// Font for italic cells. Defaults to `font_type`, since fonts can't be
+    /// selected by style.
+    pub italic_font_type: Option<Font>,
+    /// Font for bold italic cells. Defaults to a bold weight of the italic
+    /// font if there is one, and to the bold font otherwise.
+    pub bold_italic_font_type: Option<Font>,
+    pub blinking: bool,
+    /// Fonts tried in order for characters the cell font has no glyph for.
+    /// Coverage is looked up among the installed system fonts, so fonts
+    /// loaded from memory can't be used as fallbacks.
+    pub fallback_fonts: Vec<Font>,
+    /// Draw box-drawing and block element characters with lines and
+    /// rectangles instead of font glyphs, so they tile without gaps.
+    pub box_drawing: bool,
 }
 
 impl Default for FontSettings {
     fn default() -> Self {
-        Self { size: 14.0 }
+        Self {
+            size: 14.0,
+            font_type: Font::default(),
+            line_height: DEFAULT_LINE_HEIGHT,
+            letter_spacing: 0.0,
+            bold_font_type: None,
+            italic_font_type: None,
+            bold_italic_font_type: None,
+            blinking: true,
+            fallback_fonts: Vec::new(),
+            box_drawing: true,
+        }
+    }
+}
+
+impl FontSettings {
+    pub(crate) fn bold_font(&self) -> Font {
+        self.bold_font_type.unwrap_or(Font {
+            weight: Weight::Bold,
+            ..self.font_type
+        })
+    }
+
+    pub(crate) fn italic_font(&self) -> Font {
+        self.italic_font_type.unwrap_or(self.font_type)
+    }
+
+    pub(crate) fn bold_italic_font(&self) -> Font {
+        match (self.bold_italic_font_type, self.italic_font_type) {
+            (Some(font), _) => font,
+            (None, Some(italic)) => Font {
+                weight: Weight::Bold,
+                ..italic
+            },
+            (None, None) => self.bold_font(),
+        }
+    }
+}
+
+pub fn clamp_font_size(size: f32) -> f32 {
+    size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE)
+}
+
+pub fn font_measure(
+    font_size: f32,
+    font_type: Font,
+    line_height: f32,
+    letter_spacing: f32,
+) -> Size<f32> {
+    let line_height = iced::widget::text::LineHeight::Relative(line_height);
+    Size {
+        width: glyph_width(font_size, font_type) + letter_spacing,
+        height: line_height.to_absolute(Pixels(font_size)).0,
     }
 }
 
-pub fn font_measure(font_size: f32) -> Size<f32> {
+fn glyph_width(font_size: f32, font_type: Font) -> f32 {
+    static WIDTHS: OnceLock<Mutex<HashMap<(Font, u32), f32>>> = OnceLock::new();
+    let mut widths = WIDTHS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    *widths
+        .entry((font_type, font_size.to_bits()))
+        .or_insert_with(|| measure_glyph_width(font_size, font_type))
+}
+
+fn measure_glyph_width(font_size: f32, font_type: Font) -> f32 {
     let backend = Backend::new(Settings {
-        default_font: Font::default(),
+        default_font: font_type,
         default_text_size: font_size,
     });
 
@@ -27,43 +139,310 @@ pub fn font_measure(font_size: f32) -> Size<f32> {
         &renderer,
         "W",
         font_size,
-        iced::widget::text::LineHeight::Relative(1.2),
-        Font::default(),
+        iced::widget::text::LineHeight::default(),
+        font_type,
         Size {
             width: 0.0,
             height: 0.0,
         },
         iced::widget::text::Shaping::Advanced,
     )
+    .width
+}
+
+pub fn glyph_font(c: char, font: Font, fallback_fonts: &[Font]) -> Font {
+    if c.is_ascii() || fallback_fonts.is_empty() || has_glyph(font, c) {
+        return font;
+    }
+
+    fallback_fonts
+        .iter()
+        .copied()
+        .find(|fallback| has_glyph(*fallback, c))
+        .unwrap_or(font)
 }
 
-pub fn get_color(c: alacritty_terminal::vte::ansi::Color) -> Color {
+// Basic shaping never falls back to other fonts, so anything outside ASCII
+// goes through advanced shaping to still find a glyph in some system font
+// when neither the cell font nor the fallbacks have one.
+pub fn glyph_shaping(c: char) -> Shaping {
+    if c.is_ascii() {
+        Shaping::Basic
+    } else {
+        Shaping::Advanced
+    }
+}
+
+fn has_glyph(font: Font, c: char) -> bool {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    static GLYPHS: OnceLock<Mutex<HashMap<(Font, char), bool>>> =
+        OnceLock::new();
+    let mut glyphs = GLYPHS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    *glyphs.entry((font, c)).or_insert_with(|| {
+        let fonts = FONTS.get_or_init(|| {
+            let mut fonts = fontdb::Database::new();
+            fonts.load_system_fonts();
+            // The generic families the renderer maps to, see
+            // `cosmic_text::FontSystem`.
+            fonts.set_monospace_family("Fira Mono");
+            fonts.set_sans_serif_family("Fira Sans");
+            fonts.set_serif_family("DejaVu Serif");
+            fonts
+        });
+        face_has_glyph(fonts, font, c)
+    })
+}
+
+fn face_has_glyph(fonts: &fontdb::Database, font: Font, c: char) -> bool {
+    let family = match font.family {
+        Family::Name(name) => fontdb::Family::Name(name),
+        Family::Serif => fontdb::Family::Serif,
+        Family::SansSerif => fontdb::Family::SansSerif,
+        Family::Cursive => fontdb::Family::Cursive,
+        Family::Fantasy => fontdb::Family::Fantasy,
+        Family::Monospace => fontdb::Family::Monospace,
+    };
+    let query = fontdb::Query {
+        families: &[family],
+        weight: fontdb::Weight(font_weight(font.weight)),
+        stretch: font_stretch(font.stretch),
+        style: fontdb::Style::Normal,
+    };
+
+    fonts
+        .query(&query)
+        .and_then(|id| {
+            fonts.with_face_data(id, |data, index| {
+                ttf_parser::Face::parse(data, index)
+                    .ok()
+                    .and_then(|face| face.glyph_index(c))
+                    .is_some()
+            })
+        })
+        // A generic family the renderer resolves to a font that isn't
+        // installed falls back to some other font, which can't be checked
+        // here, so the glyph is assumed to be there.
+        .unwrap_or(!matches!(font.family, Family::Name(_)))
+}
+
+fn font_weight(weight: Weight) -> u16 {
+    match weight {
+        Weight::Thin => 100,
+        Weight::ExtraLight => 200,
+        Weight::Light => 300,
+        Weight::Normal => 400,
+        Weight::Medium => 500,
+        Weight::Semibold => 600,
+        Weight::Bold => 700,
+        Weight::ExtraBold => 800,
+        Weight::Black => 900,
+    }
+}
+
+fn font_stretch(stretch: Stretch) -> fontdb::Stretch {
+    match stretch {
+        Stretch::UltraCondensed => fontdb::Stretch::UltraCondensed,
+        Stretch::ExtraCondensed => fontdb::Stretch::ExtraCondensed,
+        Stretch::Condensed => fontdb::Stretch::Condensed,
+        Stretch::SemiCondensed => fontdb::Stretch::SemiCondensed,
+        Stretch::Normal => fontdb::Stretch::Normal,
+        Stretch::SemiExpanded => fontdb::Stretch::SemiExpanded,
+        Stretch::Expanded => fontdb::Stretch::Expanded,
+        Stretch::ExtraExpanded => fontdb::Stretch::ExtraExpanded,
+        Stretch::UltraExpanded => fontdb::Stretch::UltraExpanded,
+    }
+}
+
+pub fn dim_color(c: Color) -> Color {
+    Color {
+        r: c.r * DIM_FACTOR,
+        g: c.g * DIM_FACTOR,
+        b: c.b * DIM_FACTOR,
+        a: c.a,
+    }
+}
+
+// Keeps `preferred` when it is readable on `background`, otherwise falls
+// back to black or white, whichever contrasts more.
+pub fn contrasting_color(background: Color, preferred: Color) -> Color {
+    let background_luminance = relative_luminance(background);
+    if contrast_ratio(background_luminance, relative_luminance(preferred))
+        >= MIN_CONTRAST_RATIO
+    {
+        return preferred;
+    }
+
+    if contrast_ratio(background_luminance, 0.0)
+        >= contrast_ratio(background_luminance, 1.0)
+    {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}
+
+fn relative_luminance(c: Color) -> f32 {
+    let channel = |v: f32| {
+        if v <= 0.03928 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(c.r) + 0.7152 * channel(c.g) + 0.0722 * channel(c.b)
+}
+
+fn contrast_ratio(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+pub fn get_color(c: ansi::Color, palette: &ColorPalette) -> Color {
     match c {
-        alacritty_terminal::vte::ansi::Color::Spec(rgb) => {
-            Color::from_rgb8(rgb.r, rgb.g, rgb.b)
+        ansi::Color::Spec(rgb) => Color::from_rgb8(rgb.r, rgb.g, rgb.b),
+        ansi::Color::Named(c) => get_named_color(c, palette),
+        ansi::Color::Indexed(index) => get_indexed_color(index, palette),
+    }
+}
+
+fn get_indexed_color(index: u8, palette: &ColorPalette) -> Color {
+    match index {
+        0..=15 => get_named_color(ANSI_NAMED_COLORS[index as usize], palette),
+        _ => palette.indexed.get(&index).copied().unwrap_or_else(|| {
+            let (r, g, b) = indexed_rgb(index);
+            Color::from_rgb8(r, g, b)
+        }),
+    }
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        16..=231 => {
+            let index = index - 16;
+            let component = |v: u8| if v == 0 { 0 } else { v * 40 + 55 };
+            (
+                component(index / 36),
+                component((index / 6) % 6),
+                component(index % 6),
+            )
         },
-        alacritty_terminal::vte::ansi::Color::Named(c) => match c {
-            NamedColor::Foreground => Color::from_rgb8(235, 218, 177),
-            NamedColor::Background => Color::from_rgb8(40, 39, 39),
-            NamedColor::Green => Color::from_rgb8(152, 150, 26),
-            NamedColor::Red => Color::from_rgb8(203, 35, 29),
-            NamedColor::Yellow => Color::from_rgb8(214, 152, 33),
-            NamedColor::Blue => Color::from_rgb8(69, 132, 135),
-            NamedColor::Cyan => Color::from_rgb8(104, 156, 105),
-            NamedColor::Magenta => Color::from_rgb8(176, 97, 133),
-            NamedColor::White => Color::from_rgb8(168, 152, 131),
-            NamedColor::Black => Color::from_rgb8(40, 39, 39),
-            NamedColor::BrightBlack => Color::from_rgb8(146, 130, 115),
-            NamedColor::BrightRed => Color::from_rgb8(250, 72, 52),
-            NamedColor::BrightGreen => Color::from_rgb8(184, 186, 38),
-            NamedColor::BrightYellow => Color::from_rgb8(249, 188, 47),
-            NamedColor::BrightBlue => Color::from_rgb8(131, 164, 151),
-            NamedColor::BrightMagenta => Color::from_rgb8(210, 133, 154),
-            NamedColor::BrightCyan => Color::from_rgb8(142, 191, 123),
-            NamedColor::BrightWhite => Color::from_rgb8(235, 218, 177),
-            NamedColor::BrightForeground => Color::from_rgb8(235, 218, 177),
-            _ => Color::from_rgb8(40, 39, 39),
+        232..=255 => {
+            let gray = (index - 232) * 10 + 8;
+            (gray, gray, gray)
         },
-        _ => Color::from_rgb8(40, 39, 39),
+        _ => (0, 0, 0),
+    }
+}
+
+fn get_named_color(c: NamedColor, palette: &ColorPalette) -> Color {
+    match c {
+        NamedColor::Foreground => palette.foreground,
+        NamedColor::Background => palette.background,
+        NamedColor::Black => palette.black,
+        NamedColor::Red => palette.red,
+        NamedColor::Green => palette.green,
+        NamedColor::Yellow => palette.yellow,
+        NamedColor::Blue => palette.blue,
+        NamedColor::Magenta => palette.magenta,
+        NamedColor::Cyan => palette.cyan,
+        NamedColor::White => palette.white,
+        NamedColor::BrightBlack => palette.bright_black,
+        NamedColor::BrightRed => palette.bright_red,
+        NamedColor::BrightGreen => palette.bright_green,
+        NamedColor::BrightYellow => palette.bright_yellow,
+        NamedColor::BrightBlue => palette.bright_blue,
+        NamedColor::BrightMagenta => palette.bright_magenta,
+        NamedColor::BrightCyan => palette.bright_cyan,
+        NamedColor::BrightWhite => palette.bright_white,
+        NamedColor::BrightForeground => palette.foreground,
+        _ => palette.background,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{BackendSettings, Pty};
+
+    #[test]
+    fn truecolor_fg_is_converted_directly() {
+        let mut pty = Pty::new_headless(0, BackendSettings::default());
+        pty.update(b"\x1b[38;2;255;128;0mX".to_vec());
+
+        let content = pty.cells();
+        let cell = content.cells.iter().find(|cell| cell.content == 'X');
+        let fg = get_color(cell.unwrap().fg, &ColorPalette::default());
+        assert_eq!(fg, Color::from_rgb8(255, 128, 0));
+    }
+
+    #[test]
+    fn indexed_colors_follow_cube_and_ramp() {
+        let palette = ColorPalette::default();
+        let indexed = |index| get_color(ansi::Color::Indexed(index), &palette);
+
+        assert_eq!(indexed(16), Color::from_rgb8(0, 0, 0));
+        assert_eq!(indexed(196), Color::from_rgb8(255, 0, 0));
+        assert_eq!(indexed(231), Color::from_rgb8(255, 255, 255));
+        assert_eq!(indexed(232), Color::from_rgb8(8, 8, 8));
+        assert_eq!(indexed(244), Color::from_rgb8(128, 128, 128));
+        assert_eq!(indexed(255), Color::from_rgb8(238, 238, 238));
+        assert_eq!(indexed(1), palette.red);
+    }
+
+    #[test]
+    fn row_pitch_follows_line_height() {
+        let measure = font_measure(14.0, Font::MONOSPACE, 1.3, 0.0);
+        let wider = font_measure(14.0, Font::MONOSPACE, 1.3, 2.0);
+
+        assert!((measure.height - 18.2).abs() < 1e-4);
+        assert_eq!(font_measure(20.0, Font::MONOSPACE, 1.0, 0.0).height, 20.0);
+        assert_eq!(wider.height, measure.height);
+        assert_eq!(wider.width, measure.width + 2.0);
+    }
+
+    #[test]
+    fn bold_font_follows_font_type() {
+        let settings = FontSettings {
+            font_type: Font::MONOSPACE,
+            ..FontSettings::default()
+        };
+
+        assert_eq!(
+            settings.bold_font(),
+            Font {
+                weight: Weight::Bold,
+                ..Font::MONOSPACE
+            }
+        );
+    }
+
+    #[test]
+    fn bold_italic_font_follows_italic_font() {
+        let italic = Font::with_name("Italic");
+        let settings = FontSettings {
+            font_type: Font::MONOSPACE,
+            italic_font_type: Some(italic),
+            ..FontSettings::default()
+        };
+
+        assert_eq!(
+            settings.bold_italic_font(),
+            Font {
+                weight: Weight::Bold,
+                ..italic
+            }
+        );
+        assert_eq!(FontSettings::default().italic_font(), Font::default());
+    }
+
+    #[test]
+    fn generic_family_without_installed_face_keeps_glyphs() {
+        let fonts = fontdb::Database::new();
+
+        assert!(face_has_glyph(&fonts, Font::MONOSPACE, 'é'));
+        assert!(!face_has_glyph(&fonts, Font::with_name("Missing"), 'é'));
     }
 }
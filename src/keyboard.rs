@@ -0,0 +1,227 @@
+use alacritty_terminal::term::TermMode;
+use iced::keyboard::{KeyCode, Modifiers};
+
+const DEFAULT_BACKSPACE_BYTE: u8 = 0x7f;
+
+#[derive(Debug, Clone)]
+pub struct KeyboardSettings {
+    pub backspace_byte: u8,
+    /// Modifiers that make PageUp and PageDown scroll the display by a page
+    /// instead of reaching the application. `None` sends them through.
+    pub page_scroll_modifiers: Option<Modifiers>,
+    /// Modifiers that zoom the font with Plus, Minus and 0. Shift is also
+    /// accepted with Plus, which needs it on most layouts, while Shift with
+    /// Minus reaches the application as Ctrl-_. `None` sends them through.
+    pub zoom_modifiers: Option<Modifiers>,
+}
+
+impl Default for KeyboardSettings {
+    fn default() -> Self {
+        Self {
+            backspace_byte: DEFAULT_BACKSPACE_BYTE,
+            page_scroll_modifiers: Some(Modifiers::SHIFT),
+            zoom_modifiers: Some(Modifiers::CTRL),
+        }
+    }
+}
+
+pub fn key_to_bytes(
+    key_code: KeyCode,
+    modifiers: Modifiers,
+    mode: TermMode,
+    settings: &KeyboardSettings,
+) -> Option<Vec<u8>> {
+    let modifier_param = modifier_param(modifiers);
+    let app_cursor = mode.contains(TermMode::APP_CURSOR);
+    if mode.contains(TermMode::APP_KEYPAD) {
+        if let Some(c) = app_keypad_char(key_code) {
+            return Some(format!("\x1bO{}", c).into_bytes());
+        }
+    }
+
+    let sequence = match key_code {
+        KeyCode::Enter | KeyCode::NumpadEnter => {
+            return Some(alt_prefixed(b"\r", modifiers))
+        },
+        KeyCode::Tab if modifiers.shift() => String::from("\x1b[Z"),
+        KeyCode::Tab => return Some(alt_prefixed(b"\t", modifiers)),
+        KeyCode::Backspace => {
+            return Some(alt_prefixed(&[settings.backspace_byte], modifiers))
+        },
+        KeyCode::Escape => return Some(alt_prefixed(b"\x1b", modifiers)),
+        KeyCode::Delete => tilde_key(3, modifier_param),
+        KeyCode::Up => cursor_key('A', modifier_param, app_cursor),
+        KeyCode::Down => cursor_key('B', modifier_param, app_cursor),
+        KeyCode::Right => cursor_key('C', modifier_param, app_cursor),
+        KeyCode::Left => cursor_key('D', modifier_param, app_cursor),
+        KeyCode::Home => cursor_key('H', modifier_param, app_cursor),
+        KeyCode::End => cursor_key('F', modifier_param, app_cursor),
+        KeyCode::Insert => tilde_key(2, modifier_param),
+        KeyCode::PageUp => tilde_key(5, modifier_param),
+        KeyCode::PageDown => tilde_key(6, modifier_param),
+        KeyCode::F1 => cursor_key('P', modifier_param, true),
+        KeyCode::F2 => cursor_key('Q', modifier_param, true),
+        KeyCode::F3 => cursor_key('R', modifier_param, true),
+        KeyCode::F4 => cursor_key('S', modifier_param, true),
+        KeyCode::F5 => tilde_key(15, modifier_param),
+        KeyCode::F6 => tilde_key(17, modifier_param),
+        KeyCode::F7 => tilde_key(18, modifier_param),
+        KeyCode::F8 => tilde_key(19, modifier_param),
+        KeyCode::F9 => tilde_key(20, modifier_param),
+        KeyCode::F10 => tilde_key(21, modifier_param),
+        KeyCode::F11 => tilde_key(23, modifier_param),
+        KeyCode::F12 => tilde_key(24, modifier_param),
+        _ if modifiers.control() => {
+            return control_byte(key_code)
+                .map(|byte| alt_prefixed(&[byte], modifiers))
+        },
+        _ => return None,
+    };
+
+    Some(sequence.into_bytes())
+}
+
+pub fn is_app_keypad_key(key_code: KeyCode, mode: TermMode) -> bool {
+    mode.contains(TermMode::APP_KEYPAD) && app_keypad_char(key_code).is_some()
+}
+
+pub fn char_to_bytes(c: char, modifiers: Modifiers) -> Vec<u8> {
+    let mut buf = [0; 4];
+    alt_prefixed(c.encode_utf8(&mut buf).as_bytes(), modifiers)
+}
+
+fn alt_prefixed(bytes: &[u8], modifiers: Modifiers) -> Vec<u8> {
+    let mut res = Vec::with_capacity(bytes.len() + 1);
+    if modifiers.alt() {
+        res.push(0x1b);
+    }
+    res.extend_from_slice(bytes);
+    res
+}
+
+fn app_keypad_char(key_code: KeyCode) -> Option<char> {
+    let c = match key_code {
+        KeyCode::Numpad0 => 'p',
+        KeyCode::Numpad1 => 'q',
+        KeyCode::Numpad2 => 'r',
+        KeyCode::Numpad3 => 's',
+        KeyCode::Numpad4 => 't',
+        KeyCode::Numpad5 => 'u',
+        KeyCode::Numpad6 => 'v',
+        KeyCode::Numpad7 => 'w',
+        KeyCode::Numpad8 => 'x',
+        KeyCode::Numpad9 => 'y',
+        KeyCode::NumpadEnter => 'M',
+        KeyCode::NumpadMultiply => 'j',
+        KeyCode::NumpadAdd => 'k',
+        KeyCode::NumpadComma => 'l',
+        KeyCode::NumpadSubtract => 'm',
+        KeyCode::NumpadDecimal => 'n',
+        KeyCode::NumpadDivide => 'o',
+        KeyCode::NumpadEquals => 'X',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+fn control_byte(key_code: KeyCode) -> Option<u8> {
+    let byte = match key_code {
+        KeyCode::A => 0x01,
+        KeyCode::B => 0x02,
+        KeyCode::C => 0x03,
+        KeyCode::D => 0x04,
+        KeyCode::E => 0x05,
+        KeyCode::F => 0x06,
+        KeyCode::G => 0x07,
+        KeyCode::H => 0x08,
+        KeyCode::I => 0x09,
+        KeyCode::J => 0x0a,
+        KeyCode::K => 0x0b,
+        KeyCode::L => 0x0c,
+        KeyCode::M => 0x0d,
+        KeyCode::N => 0x0e,
+        KeyCode::O => 0x0f,
+        KeyCode::P => 0x10,
+        KeyCode::Q => 0x11,
+        KeyCode::R => 0x12,
+        KeyCode::S => 0x13,
+        KeyCode::T => 0x14,
+        KeyCode::U => 0x15,
+        KeyCode::V => 0x16,
+        KeyCode::W => 0x17,
+        KeyCode::X => 0x18,
+        KeyCode::Y => 0x19,
+        KeyCode::Z => 0x1a,
+        KeyCode::Space | KeyCode::Key2 | KeyCode::At => 0x00,
+        KeyCode::LBracket | KeyCode::Key3 => 0x1b,
+        KeyCode::Backslash | KeyCode::Key4 => 0x1c,
+        KeyCode::RBracket | KeyCode::Key5 => 0x1d,
+        KeyCode::Key6 | KeyCode::Caret => 0x1e,
+        KeyCode::Slash | KeyCode::Minus | KeyCode::Key7 => 0x1f,
+        _ => return None,
+    };
+
+    Some(byte)
+}
+
+fn modifier_param(modifiers: Modifiers) -> u8 {
+    let mut param = 1;
+    if modifiers.shift() {
+        param += 1;
+    }
+    if modifiers.alt() {
+        param += 2;
+    }
+    if modifiers.control() {
+        param += 4;
+    }
+
+    param
+}
+
+fn cursor_key(c: char, modifier_param: u8, app_cursor: bool) -> String {
+    if modifier_param > 1 {
+        format!("\x1b[1;{}{}", modifier_param, c)
+    } else if app_cursor {
+        format!("\x1bO{}", c)
+    } else {
+        format!("\x1b[{}", c)
+    }
+}
+
+fn tilde_key(code: u8, modifier_param: u8) -> String {
+    if modifier_param > 1 {
+        format!("\x1b[{};{}~", code, modifier_param)
+    } else {
+        format!("\x1b[{}~", code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{BackendSettings, Pty};
+
+    #[test]
+    fn up_arrow_follows_cursor_key_mode() {
+        let mut pty = Pty::new_headless(0, BackendSettings::default());
+        let settings = KeyboardSettings::default();
+        let up = |pty: &Pty| {
+            key_to_bytes(KeyCode::Up, Modifiers::empty(), pty.mode(), &settings)
+        };
+
+        assert_eq!(up(&pty), Some(b"\x1b[A".to_vec()));
+        pty.update(b"\x1b[?1h".to_vec());
+        assert_eq!(up(&pty), Some(b"\x1bOA".to_vec()));
+        pty.update(b"\x1b[?1l".to_vec());
+        assert_eq!(up(&pty), Some(b"\x1b[A".to_vec()));
+    }
+
+    #[test]
+    fn alt_prefixes_characters_with_escape() {
+        assert_eq!(char_to_bytes('b', Modifiers::ALT), b"\x1bb");
+        assert_eq!(char_to_bytes('b', Modifiers::empty()), b"b");
+        assert_eq!(char_to_bytes('é', Modifiers::ALT), "\x1bé".as_bytes());
+    }
+}
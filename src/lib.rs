@@ -1,7 +1,23 @@
+// The backend relies on unix ptys, process groups and libc.
+#[cfg(not(unix))]
+compile_error!("iced_term only supports unix platforms");
+
 mod backend;
+mod box_drawing;
+mod cursor;
 mod font;
+mod keyboard;
+mod mouse;
 mod term;
+mod theme;
 
-pub use backend::BackendSettings;
+pub use backend::{
+    BackendSettings, BackendSettingsBuilder, ClipboardFormatter,
+    ColorFormatter, CommandRegion, DynamicColor, Error, GridRange, Osc52,
+    ScrollState, TermMode, TerminalEvent, TextAreaSizeFormatter,
+};
+pub use cursor::CursorSettings;
 pub use font::FontSettings;
-pub use term::{Command, Event, Term, TermSettings};
+pub use keyboard::KeyboardSettings;
+pub use term::{Command, ContextMenu, Event, Term, TermSettings};
+pub use theme::{ColorPalette, ThemeSettings};
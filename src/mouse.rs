@@ -0,0 +1,109 @@
+use alacritty_terminal::term::TermMode;
+use iced::keyboard::Modifiers;
+use iced::mouse::Button;
+
+const LEFT_BUTTON: u8 = 0;
+const MIDDLE_BUTTON: u8 = 1;
+const RIGHT_BUTTON: u8 = 2;
+const RELEASE: u8 = 3;
+const MOTION: u8 = 32;
+const WHEEL_UP: u8 = 64;
+const WHEEL_DOWN: u8 = 65;
+const MAX_LEGACY_COORDINATE: usize = 223;
+const MAX_UTF8_COORDINATE: usize = 2015;
+
+pub fn button_code(button: Button) -> Option<u8> {
+    match button {
+        Button::Left => Some(LEFT_BUTTON),
+        Button::Middle => Some(MIDDLE_BUTTON),
+        Button::Right => Some(RIGHT_BUTTON),
+        Button::Other(_) => None,
+    }
+}
+
+pub fn motion_code(pressed_button: Option<u8>, mode: TermMode) -> Option<u8> {
+    match pressed_button {
+        Some(code)
+            if mode
+                .intersects(TermMode::MOUSE_DRAG | TermMode::MOUSE_MOTION) =>
+        {
+            Some(code + MOTION)
+        },
+        None if mode.contains(TermMode::MOUSE_MOTION) => Some(RELEASE + MOTION),
+        _ => None,
+    }
+}
+
+pub fn wheel_code(delta: f32) -> u8 {
+    if delta > 0.0 {
+        WHEEL_UP
+    } else {
+        WHEEL_DOWN
+    }
+}
+
+pub fn report(
+    code: u8,
+    pressed: bool,
+    column: usize,
+    line: usize,
+    modifiers: Modifiers,
+    mode: TermMode,
+) -> Option<Vec<u8>> {
+    let code = code + modifier_bits(modifiers);
+    if mode.contains(TermMode::SGR_MOUSE) {
+        let suffix = if pressed { 'M' } else { 'm' };
+        let sequence =
+            format!("\x1b[<{};{};{}{}", code, column + 1, line + 1, suffix);
+        return Some(sequence.into_bytes());
+    }
+
+    let code = if pressed { code } else { code | RELEASE };
+    let utf8 = mode.contains(TermMode::UTF8_MOUSE);
+    let mut bytes = b"\x1b[M".to_vec();
+    bytes.push(32 + code);
+    push_coordinate(&mut bytes, column, utf8)?;
+    push_coordinate(&mut bytes, line, utf8)?;
+
+    Some(bytes)
+}
+
+fn modifier_bits(modifiers: Modifiers) -> u8 {
+    let mut bits = 0;
+    if modifiers.shift() {
+        bits += 4;
+    }
+    if modifiers.alt() {
+        bits += 8;
+    }
+    if modifiers.control() {
+        bits += 16;
+    }
+
+    bits
+}
+
+fn push_coordinate(
+    bytes: &mut Vec<u8>,
+    coordinate: usize,
+    utf8: bool,
+) -> Option<()> {
+    if !utf8 {
+        if coordinate >= MAX_LEGACY_COORDINATE {
+            return None;
+        }
+
+        bytes.push(32 + 1 + coordinate as u8);
+        return Some(());
+    }
+
+    if coordinate >= MAX_UTF8_COORDINATE {
+        return None;
+    }
+
+    let c = char::from_u32(32 + 1 + coordinate as u32)?;
+    let mut buf = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+
+    Some(())
+}
@@ -1,68 +1,285 @@
-use crate::backend::{BackendSettings, Pty, RenderableCell};
-use crate::{font, FontSettings};
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+use crate::backend::ImageData;
+use crate::backend::{
+    self, BackendSettings, ClipboardFormatter, ColorFormatter, CommandRegion,
+    CursorShape, DynamicColor, GridRange, Pty, RenderableCell,
+    RenderableContent, RenderableLink, ScrollState, TerminalEvent,
+    TextAreaSizeFormatter,
+};
+use crate::{
+    box_drawing, font, keyboard, mouse, CursorSettings, FontSettings,
+    KeyboardSettings, ThemeSettings,
+};
+use alacritty_terminal::index::Side;
+use alacritty_terminal::selection::SelectionType;
+use alacritty_terminal::term::{cell, TermMode};
+use alacritty_terminal::vte::ansi::{self, NamedColor};
+#[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+use iced::advanced::image;
 use iced::alignment::{Horizontal, Vertical};
-use iced::mouse::{Cursor, ScrollDelta};
-use iced::widget::canvas::{Cache, Path, Text};
+use iced::keyboard::{KeyCode, Modifiers};
+use iced::mouse::{Button, Cursor, Interaction, ScrollDelta};
+use iced::widget::canvas::{Cache, Frame, Path, Stroke, Text};
 use iced::widget::container;
+use iced::window;
 use iced::{
     Color, Element, Font, Length, Point, Rectangle, Size, Subscription, Theme,
 };
-use iced_graphics::core::widget::Tree;
+use iced_graphics::core::mouse::click::{self, Click};
+use iced_graphics::core::widget::{tree, Tree};
+use iced_graphics::core::Clipboard;
 use iced_graphics::core::Widget;
 use iced_graphics::geometry::Renderer;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const LINE_THICKNESS: f32 = 1.0;
+const REDRAW_INTERVAL_MS: u64 = 16;
+const FONT_SIZE_STEP: f32 = 1.0;
+const TEXT_BLINK_INTERVAL_MS: u64 = 500;
+const RESIZE_INTERVAL_MS: u64 = 50;
 
 #[derive(Debug, Clone)]
 pub enum Event {
     DataUpdated(u64, Vec<u8>),
+    CursorBlinked(u64),
+    TextBlinked(u64),
+    RedrawRequested(u64),
+    TitleChanged(u64, String),
+    TitleReset(u64),
+    WorkingDirectoryChanged(u64, PathBuf),
+    ColorChanged(u64, DynamicColor, Option<Color>),
+    ColorRequested(u64, DynamicColor, ColorFormatter),
+    TextAreaSizeRequested(u64, TextAreaSizeFormatter),
+    Bell(u64),
+    ProcessExited(u64, Option<i32>),
     InputReceived(u64, char),
+    KeyInputReceived(u64, Vec<u8>),
+    ReplyRequested(u64, String),
+    MouseReported(u64, Vec<u8>),
+    TextPasted(u64, String),
+    TextCopied(u64, String),
+    ClipboardStoreRequested(u64, String),
+    ClipboardLoadRequested(u64, ClipboardFormatter),
     ContainerScrolled(u64, f32),
+    PreviousPromptRequested(u64),
+    NextPromptRequested(u64),
+    SelectionStarted(u64, Point, SelectionType),
+    SelectionUpdated(u64, Point),
     Resized(u64, Size<f32>),
+    FontSizeChanged(u64, f32),
+    LinkClicked(u64, String),
+    ContextMenuRequested(u64, ContextMenu),
     Ignored(u64),
 }
 
+impl Event {
+    pub fn id(&self) -> u64 {
+        match self {
+            Event::DataUpdated(id, _)
+            | Event::CursorBlinked(id)
+            | Event::TextBlinked(id)
+            | Event::RedrawRequested(id)
+            | Event::TitleChanged(id, _)
+            | Event::WorkingDirectoryChanged(id, _)
+            | Event::ColorChanged(id, ..)
+            | Event::ColorRequested(id, ..)
+            | Event::TextAreaSizeRequested(id, _)
+            | Event::TitleReset(id)
+            | Event::Bell(id)
+            | Event::ProcessExited(id, _)
+            | Event::InputReceived(id, _)
+            | Event::KeyInputReceived(id, _)
+            | Event::ReplyRequested(id, _)
+            | Event::MouseReported(id, _)
+            | Event::TextPasted(id, _)
+            | Event::TextCopied(id, _)
+            | Event::ClipboardStoreRequested(id, _)
+            | Event::ClipboardLoadRequested(id, _)
+            | Event::ContainerScrolled(id, _)
+            | Event::PreviousPromptRequested(id)
+            | Event::NextPromptRequested(id)
+            | Event::SelectionStarted(id, _, _)
+            | Event::SelectionUpdated(id, _)
+            | Event::Resized(id, _)
+            | Event::FontSizeChanged(id, _)
+            | Event::LinkClicked(id, _)
+            | Event::ContextMenuRequested(id, _)
+            | Event::Ignored(id) => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextMenu {
+    pub position: Point,
+    pub column: usize,
+    pub line: usize,
+    pub has_selection: bool,
+}
+
 #[derive(Debug, Clone)]
 pub enum Command {
     Focus,
     LostFocus,
     WriteToPTY(char),
+    WriteStringToPTY(String),
+    WriteBytesToPTY(Vec<u8>),
+    ReplyToPTY(String),
+    ReplyColor(DynamicColor, ColorFormatter),
+    ReplyTextAreaSize(TextAreaSizeFormatter),
+    Paste(String),
     RenderData(Vec<u8>),
+    Redraw,
+    Reset,
+    ClearScrollback,
+    BlinkCursor,
+    BlinkText,
     Scroll(i32),
+    ScrollToTop,
+    ScrollToBottom,
+    ScrollToPreviousPrompt,
+    ScrollToNextPrompt,
+    StartSelection(Point, SelectionType),
+    UpdateSelection(Point),
     Resize(Size<f32>),
+    ChangeTheme(Box<ThemeSettings>),
+    ChangeColor(DynamicColor, Option<Color>),
+    ChangeFontSize(f32),
 }
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct TermSettings {
     pub font: FontSettings,
     pub backend: BackendSettings,
+    pub cursor: CursorSettings,
+    pub theme: ThemeSettings,
+    pub keyboard: KeyboardSettings,
+    /// Fit the grid to the widget bounds. The terminal is spawned with
+    /// `backend.cols` and `backend.rows`, resized on the first layout and
+    /// not drawn until then, so the configured size never shows on screen.
+    /// When disabled the grid keeps the configured size, and is only
+    /// resized by the host through `Command::Resize`.
+    pub auto_fit: bool,
+}
+
+impl Default for TermSettings {
+    fn default() -> Self {
+        Self {
+            font: FontSettings::default(),
+            backend: BackendSettings::default(),
+            cursor: CursorSettings::default(),
+            theme: ThemeSettings::default(),
+            keyboard: KeyboardSettings::default(),
+            auto_fit: true,
+        }
+    }
 }
 
 pub struct Term {
     id: u64,
     font_size: f32,
+    default_font_size: f32,
+    font_type: Font,
+    line_height: f32,
+    letter_spacing: f32,
+    bold_font: Font,
+    italic_font: Font,
+    bold_italic_font: Font,
+    fallback_fonts: Vec<Font>,
+    box_drawing: bool,
     font_measure: Size<f32>,
     padding: u16,
-    cache: Cache,
+    line_caches: Vec<Cache>,
+    cursor_cache: Cache,
     is_focused: bool,
-    renderable_content: Vec<RenderableCell>,
+    show_cursor: bool,
+    cursor_blinking: bool,
+    cursor_blink_interval: Duration,
+    cursor_blink_visible: bool,
+    text_blinking: bool,
+    text_blink_visible: bool,
+    renderable_content: RenderableContent,
+    search_matches: Vec<GridRange>,
+    current_search_match: Option<usize>,
+    // Matches overlapping the viewport with their index in `search_matches`,
+    // sorted by position.
+    visible_search_matches: Vec<(usize, GridRange)>,
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    image_handles: HashMap<u64, image::Handle>,
+    is_dirty: bool,
+    theme: ThemeSettings,
+    // `theme` with the colors set by the program applied.
+    active_theme: ThemeSettings,
+    color_overrides: HashMap<DynamicColor, Color>,
+    keyboard: KeyboardSettings,
     backend: Pty,
     size: Size<f32>,
+    auto_fit: bool,
 }
 
 impl Term {
     pub fn new(id: u64, settings: TermSettings) -> Self {
+        Self::try_new(id, settings).expect("failed to create terminal")
+    }
+
+    pub fn try_new(id: u64, settings: TermSettings) -> backend::Result<Self> {
+        let backend = Pty::new(id, settings.backend.clone())?;
+        Ok(Self::with_backend(id, settings, backend))
+    }
+
+    fn with_backend(id: u64, settings: TermSettings, mut backend: Pty) -> Self {
+        let font_measure = font::font_measure(
+            settings.font.size,
+            settings.font.font_type,
+            settings.font.line_height,
+            settings.font.letter_spacing,
+        );
+        let (rows, cols) = (settings.backend.rows, settings.backend.cols);
+        backend.resize(rows, cols, font_measure.width, font_measure.height);
+
         Self {
             id,
             font_size: settings.font.size,
-            font_measure: font::font_measure(settings.font.size),
+            default_font_size: settings.font.size,
+            font_type: settings.font.font_type,
+            line_height: settings.font.line_height,
+            letter_spacing: settings.font.letter_spacing,
+            bold_font: settings.font.bold_font(),
+            italic_font: settings.font.italic_font(),
+            bold_italic_font: settings.font.bold_italic_font(),
+            fallback_fonts: settings.font.fallback_fonts,
+            box_drawing: settings.font.box_drawing,
+            font_measure,
             padding: 0,
             is_focused: true,
-            renderable_content: vec![],
-            cache: Cache::default(),
-            backend: Pty::new(id, settings.backend).unwrap(),
+            show_cursor: settings.cursor.visible,
+            cursor_blinking: settings.cursor.blinking,
+            cursor_blink_interval: settings.cursor.blink_interval,
+            cursor_blink_visible: true,
+            text_blinking: settings.font.blinking,
+            text_blink_visible: true,
+            renderable_content: RenderableContent::default(),
+            search_matches: Vec::new(),
+            current_search_match: None,
+            visible_search_matches: Vec::new(),
+            #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+            image_handles: HashMap::new(),
+            is_dirty: false,
+            active_theme: settings.theme.clone(),
+            theme: settings.theme,
+            color_overrides: HashMap::new(),
+            keyboard: settings.keyboard,
+            line_caches: (0..rows).map(|_| Cache::default()).collect(),
+            cursor_cache: Cache::default(),
+            backend,
             size: Size {
                 width: 0.0,
                 height: 0.0,
             },
+            auto_fit: settings.auto_fit,
         }
     }
 
@@ -70,100 +287,1333 @@ impl Term {
         self.id
     }
 
+    pub fn grid_size(&self) -> (u16, u16) {
+        self.backend.size()
+    }
+
+    pub fn grid_text(&self) -> Vec<String> {
+        self.backend.grid_text()
+    }
+
+    pub fn search(
+        &self,
+        query: &str,
+        case_insensitive: bool,
+    ) -> Vec<GridRange> {
+        self.backend.search(query, case_insensitive)
+    }
+
+    pub fn set_search_matches(
+        &mut self,
+        matches: Vec<GridRange>,
+        current: Option<usize>,
+    ) {
+        self.search_matches = matches;
+        self.current_search_match = current;
+        self.update_visible_search_matches();
+        self.clear_caches();
+    }
+
+    fn update_visible_search_matches(&mut self) {
+        let top = -(self.renderable_content.display_offset as i32);
+        let bottom = top + i32::from(self.grid_size().1) - 1;
+        self.visible_search_matches = self
+            .search_matches
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, range)| {
+                range.end_line >= top && range.start_line <= bottom
+            })
+            .collect();
+        self.visible_search_matches
+            .sort_by_key(|(_, range)| (range.start_line, range.start_column));
+    }
+
+    pub fn clear_search_matches(&mut self) {
+        self.set_search_matches(Vec::new(), None);
+    }
+
+    pub fn is_alt_screen(&self) -> bool {
+        self.backend.is_alt_screen()
+    }
+
+    pub fn mode(&self) -> TermMode {
+        self.backend.mode()
+    }
+
+    pub fn is_app_cursor(&self) -> bool {
+        self.backend.mode().contains(TermMode::APP_CURSOR)
+    }
+
+    pub fn bracketed_paste_enabled(&self) -> bool {
+        self.backend.bracketed_paste_enabled()
+    }
+
+    pub fn selection_text(&self) -> Option<String> {
+        self.backend.selection_text()
+    }
+
+    pub fn selection_range(&self) -> Option<GridRange> {
+        self.backend.selection_range()
+    }
+
+    pub fn child_pid(&self) -> Option<u32> {
+        self.backend.child_pid()
+    }
+
+    pub fn foreground_process(&self) -> Option<String> {
+        self.backend.foreground_process()
+    }
+
+    pub fn working_directory(&self) -> Option<PathBuf> {
+        self.backend.working_directory()
+    }
+
+    pub fn send_signal(&self, signal: i32) -> backend::Result<()> {
+        self.backend.send_signal(signal)
+    }
+
+    pub fn kill(&self) -> backend::Result<()> {
+        self.backend.kill()
+    }
+
+    pub fn command_regions(&self) -> Vec<CommandRegion> {
+        self.backend.command_regions()
+    }
+
+    pub fn scroll_state(&self) -> ScrollState {
+        self.backend.scroll_state()
+    }
+
+    pub fn subscription(&self) -> Subscription<Event> {
+        Subscription::batch(vec![
+            self.data_subscription(),
+            self.backend_event_subscription(),
+            self.cursor_blink_subscription(),
+            self.text_blink_subscription(),
+            self.redraw_subscription(),
+        ])
+    }
+
     pub fn data_subscription(&self) -> Subscription<Event> {
+        let Some(reader) = self.backend.reader() else {
+            return Subscription::none();
+        };
+        let pid = reader.pid();
+
         iced::subscription::unfold(
             format!("iced_term_{}", self.id),
-            (self.id, self.backend.reader()),
-            move |(id, reader)| async move {
-                match Pty::read(&reader).await {
-                    Some(data) => (Event::DataUpdated(id, data), (id, reader)),
-                    None => (Event::Ignored(id), (id, reader)),
+            (self.id, reader, pid, false),
+            move |(id, mut reader, pid, exited)| async move {
+                if exited {
+                    return std::future::pending().await;
                 }
+
+                match Pty::read(&mut reader).await {
+                    Ok(data) => {
+                        (Event::DataUpdated(id, data), (id, reader, pid, false))
+                    },
+                    Err(err)
+                        if err.kind() == ErrorKind::WouldBlock
+                            || err.kind() == ErrorKind::Interrupted =>
+                    {
+                        (Event::Ignored(id), (id, reader, pid, false))
+                    },
+                    Err(_) => {
+                        let code = Pty::wait_exit_code(pid).await;
+                        (
+                            Event::ProcessExited(id, code),
+                            (id, reader, pid, true),
+                        )
+                    },
+                }
+            },
+        )
+    }
+
+    pub fn backend_event_subscription(&self) -> Subscription<Event> {
+        iced::subscription::unfold(
+            format!("iced_term_events_{}", self.id),
+            (self.id, self.backend.events()),
+            move |(id, events)| async move {
+                let event = events.lock().await.recv().await;
+                let term_event = match event {
+                    Some(TerminalEvent::Title(title)) => {
+                        Event::TitleChanged(id, title)
+                    },
+                    Some(TerminalEvent::TitleReset) => Event::TitleReset(id),
+                    Some(TerminalEvent::WorkingDirectoryChanged(path)) => {
+                        Event::WorkingDirectoryChanged(id, path)
+                    },
+                    Some(TerminalEvent::ColorChanged(color, value)) => {
+                        let value = value
+                            .map(|rgb| Color::from_rgb8(rgb.r, rgb.g, rgb.b));
+                        Event::ColorChanged(id, color, value)
+                    },
+                    Some(TerminalEvent::ColorRequest(color, formatter)) => {
+                        Event::ColorRequested(id, color, formatter)
+                    },
+                    Some(TerminalEvent::TextAreaSizeRequest(formatter)) => {
+                        Event::TextAreaSizeRequested(id, formatter)
+                    },
+                    Some(TerminalEvent::Bell) => Event::Bell(id),
+                    Some(TerminalEvent::PtyWrite(text)) => {
+                        Event::ReplyRequested(id, text)
+                    },
+                    Some(TerminalEvent::ClipboardStore(text)) => {
+                        Event::ClipboardStoreRequested(id, text)
+                    },
+                    Some(TerminalEvent::ClipboardLoad(formatter)) => {
+                        Event::ClipboardLoadRequested(id, formatter)
+                    },
+                    None => std::future::pending().await,
+                };
+
+                (term_event, (id, events))
             },
         )
     }
 
+    pub fn cursor_blink_subscription(&self) -> Subscription<Event> {
+        if !self.show_cursor || !self.cursor_blinking || !self.is_focused {
+            return Subscription::none();
+        }
+
+        iced::time::every(self.cursor_blink_interval)
+            .with(self.id)
+            .map(|(id, _)| Event::CursorBlinked(id))
+    }
+
+    pub fn text_blink_subscription(&self) -> Subscription<Event> {
+        if !self.text_blinking
+            || !self
+                .renderable_content
+                .cells
+                .iter()
+                .any(RenderableCell::is_blinking)
+        {
+            return Subscription::none();
+        }
+
+        iced::time::every(Duration::from_millis(TEXT_BLINK_INTERVAL_MS))
+            .with(self.id)
+            .map(|(id, _)| Event::TextBlinked(id))
+    }
+
+    pub fn redraw_subscription(&self) -> Subscription<Event> {
+        if !self.is_dirty {
+            return Subscription::none();
+        }
+
+        iced::time::every(Duration::from_millis(REDRAW_INTERVAL_MS))
+            .with(self.id)
+            .map(|(id, _)| Event::RedrawRequested(id))
+    }
+
     pub fn update(&mut self, cmd: Command) {
         match cmd {
             Command::Focus => {
                 self.is_focused = true;
+                self.cursor_blink_visible = true;
+                self.cursor_cache.clear();
             },
             Command::LostFocus => {
                 self.is_focused = false;
+                self.cursor_cache.clear();
             },
             Command::WriteToPTY(c) => {
-                self.backend.write_to_pty(c);
+                let result = self.backend.write_to_pty(c);
+                self.on_input_written(result);
+            },
+            Command::WriteStringToPTY(s) => {
+                let result = self.backend.write_str(&s);
+                self.on_input_written(result);
+            },
+            Command::WriteBytesToPTY(bytes) => {
+                let result = self.backend.write_bytes(&bytes);
+                self.on_input_written(result);
+            },
+            Command::ReplyToPTY(text) => {
+                let _ = self.backend.write_reply(&text);
+            },
+            Command::ReplyColor(color, formatter) => {
+                let [r, g, b, _] = self.dynamic_color(color).into_rgba8();
+                let reply = formatter.format(ansi::Rgb { r, g, b });
+                let _ = self.backend.write_reply(&reply);
+            },
+            Command::ReplyTextAreaSize(formatter) => {
+                let reply = formatter.format(self.backend.window_size());
+                let _ = self.backend.write_reply(&reply);
+            },
+            Command::Paste(text) => {
+                let result = self.backend.paste(&text);
+                self.on_input_written(result);
             },
             Command::RenderData(data) => {
-                let content = self.backend.update(data);
-                self.renderable_content = content;
-                self.cache.clear();
+                self.backend.update(data);
+                self.is_dirty = true;
+                self.reset_cursor_blink();
+            },
+            Command::Redraw => {
+                if self.is_dirty {
+                    let content = self.backend.cells();
+                    match self.backend.damage() {
+                        Some(lines) => {
+                            self.renderable_content = content;
+                            #[cfg(any(
+                                feature = "sixel",
+                                feature = "kitty-graphics"
+                            ))]
+                            self.sync_image_handles();
+                            self.is_dirty = false;
+                            for line in lines {
+                                if let Some(cache) = self.line_caches.get(line)
+                                {
+                                    cache.clear();
+                                }
+                            }
+                            self.cursor_cache.clear();
+                        },
+                        None => self.set_content(content),
+                    }
+                }
+            },
+            Command::Reset => {
+                let content = self.backend.reset();
+                self.set_content(content);
+            },
+            Command::ClearScrollback => {
+                let content = self.backend.clear_scrollback();
+                self.set_content(content);
+            },
+            Command::BlinkCursor => {
+                self.cursor_blink_visible = !self.cursor_blink_visible;
+                self.cursor_cache.clear();
+            },
+            Command::BlinkText => {
+                self.text_blink_visible = !self.text_blink_visible;
+                self.clear_blinking_lines();
             },
             Command::Scroll(delta) => {
                 let content = self.backend.scroll(delta);
-                self.renderable_content = content;
-                self.cache.clear();
-            },
-            Command::Resize(size) => {
-                let container_padding =
-                    f32::from(self.padding.saturating_mul(2));
-                let container_width = (size.width - container_padding).max(1.0);
-                let container_height =
-                    (size.height - container_padding).max(1.0);
-                let rows = (container_height / self.font_measure.height).floor()
-                    as u16;
-                let cols =
-                    (container_width / self.font_measure.width).floor() as u16;
-                let content = self.backend.resize(
-                    rows,
-                    cols,
-                    self.font_measure.width,
-                    self.font_measure.height,
+                self.set_content(content);
+            },
+            Command::ScrollToTop => {
+                let content = self.backend.scroll_to_top();
+                self.set_content(content);
+            },
+            Command::ScrollToBottom => {
+                let content = self.backend.scroll_to_bottom();
+                self.set_content(content);
+            },
+            Command::ScrollToPreviousPrompt => {
+                let content = self.backend.scroll_to_prev_prompt();
+                self.set_content(content);
+            },
+            Command::ScrollToNextPrompt => {
+                let content = self.backend.scroll_to_next_prompt();
+                self.set_content(content);
+            },
+            Command::StartSelection(position, selection_type) => {
+                let (line, column, side) = self.selection_point(position);
+                let content = self.backend.start_selection(
+                    selection_type,
+                    line,
+                    column,
+                    side,
                 );
-                self.renderable_content = content;
-                self.cache.clear();
-                self.size = size;
+                self.set_content(content);
+            },
+            Command::UpdateSelection(position) => {
+                if position.y < 0.0 {
+                    self.backend.scroll(1);
+                } else if position.y > self.size.height {
+                    self.backend.scroll(-1);
+                }
+
+                let (line, column, side) = self.selection_point(position);
+                let content = self.backend.update_selection(line, column, side);
+                self.set_content(content);
+            },
+            Command::Resize(size) => self.resize(size),
+            Command::ChangeTheme(theme) => {
+                self.theme = *theme;
+                self.apply_color_overrides();
+            },
+            Command::ChangeColor(color, value) => {
+                match value {
+                    Some(value) => self.color_overrides.insert(color, value),
+                    None => self.color_overrides.remove(&color),
+                };
+                self.apply_color_overrides();
+            },
+            Command::ChangeFontSize(size) => self.set_font_size(size),
+        }
+    }
+
+    fn dynamic_color(&self, color: DynamicColor) -> Color {
+        let palette = &self.active_theme.palette;
+        match color {
+            DynamicColor::Indexed(index) => {
+                font::get_color(ansi::Color::Indexed(index), palette)
             },
+            DynamicColor::Foreground => palette.foreground,
+            DynamicColor::Background => palette.background,
+            DynamicColor::Cursor => {
+                palette.cursor.unwrap_or(palette.foreground)
+            },
+        }
+    }
+
+    // Colors set by the program take precedence over the theme until they
+    // are reset, and survive theme changes.
+    fn apply_color_overrides(&mut self) {
+        self.active_theme = self.theme.clone();
+        for (color, value) in &self.color_overrides {
+            match color {
+                DynamicColor::Indexed(index) => {
+                    self.active_theme.palette.set_indexed(*index, *value)
+                },
+                DynamicColor::Foreground => {
+                    self.active_theme.palette.foreground = *value
+                },
+                DynamicColor::Background => {
+                    self.active_theme.palette.background = *value
+                },
+                DynamicColor::Cursor => {
+                    self.active_theme.palette.cursor = Some(*value)
+                },
+            }
+        }
+        self.clear_caches();
+    }
+
+    pub fn set_font_size(&mut self, size: f32) {
+        self.font_size = font::clamp_font_size(size);
+        self.remeasure_font();
+    }
+
+    pub fn set_line_height(&mut self, line_height: f32) {
+        self.line_height = line_height;
+        self.remeasure_font();
+    }
+
+    pub fn set_letter_spacing(&mut self, letter_spacing: f32) {
+        self.letter_spacing = letter_spacing;
+        self.remeasure_font();
+    }
+
+    fn remeasure_font(&mut self) {
+        self.font_measure = font::font_measure(
+            self.font_size,
+            self.font_type,
+            self.line_height,
+            self.letter_spacing,
+        );
+        if self.auto_fit {
+            self.resize(self.size);
+        } else {
+            let (cols, rows) = self.backend.size();
+            let content = self.backend.resize(
+                rows,
+                cols,
+                self.font_measure.width,
+                self.font_measure.height,
+            );
+            self.set_content(content);
         }
     }
 
-    pub fn view(&self) -> Element<Event> {
+    fn resize(&mut self, size: Size<f32>) {
+        let container_padding = f32::from(self.padding.saturating_mul(2));
+        let container_width = (size.width - container_padding).max(1.0);
+        let container_height = (size.height - container_padding).max(1.0);
+        let rows = (container_height / self.font_measure.height)
+            .floor()
+            .max(1.0) as u16;
+        let cols =
+            (container_width / self.font_measure.width).floor().max(1.0) as u16;
+        let content = self.backend.resize(
+            rows,
+            cols,
+            self.font_measure.width,
+            self.font_measure.height,
+        );
+        self.line_caches.resize_with(rows as usize, Cache::default);
+        self.set_content(content);
+        self.size = size;
+    }
+
+    /// Applies an event published by this terminal. Events carrying the id
+    /// of another terminal are ignored.
+    pub fn handle_event(&mut self, event: Event) {
+        if event.id() != self.id {
+            return;
+        }
+
+        let cmd = match event {
+            Event::DataUpdated(_, data) => Command::RenderData(data),
+            Event::CursorBlinked(_) => Command::BlinkCursor,
+            Event::TextBlinked(_) => Command::BlinkText,
+            Event::RedrawRequested(_) => Command::Redraw,
+            Event::InputReceived(_, c) => Command::WriteToPTY(c),
+            Event::KeyInputReceived(_, bytes)
+            | Event::MouseReported(_, bytes) => Command::WriteBytesToPTY(bytes),
+            Event::ReplyRequested(_, text) => Command::ReplyToPTY(text),
+            Event::ColorRequested(_, color, formatter) => {
+                Command::ReplyColor(color, formatter)
+            },
+            Event::TextAreaSizeRequested(_, formatter) => {
+                Command::ReplyTextAreaSize(formatter)
+            },
+            Event::TextPasted(_, text) => Command::Paste(text),
+            Event::ContainerScrolled(_, delta) => Command::Scroll(delta as i32),
+            Event::PreviousPromptRequested(_) => {
+                Command::ScrollToPreviousPrompt
+            },
+            Event::NextPromptRequested(_) => Command::ScrollToNextPrompt,
+            Event::SelectionStarted(_, position, selection_type) => {
+                Command::StartSelection(position, selection_type)
+            },
+            Event::SelectionUpdated(_, position) => {
+                Command::UpdateSelection(position)
+            },
+            Event::Resized(_, size) => Command::Resize(size),
+            Event::FontSizeChanged(_, size) => Command::ChangeFontSize(size),
+            Event::ColorChanged(_, color, value) => {
+                Command::ChangeColor(color, value)
+            },
+            Event::TitleChanged(..)
+            | Event::TitleReset(_)
+            | Event::WorkingDirectoryChanged(..)
+            | Event::Bell(_)
+            | Event::ProcessExited(..)
+            | Event::TextCopied(..)
+            | Event::ClipboardStoreRequested(..)
+            | Event::ClipboardLoadRequested(..)
+            | Event::LinkClicked(..)
+            | Event::ContextMenuRequested(..)
+            | Event::Ignored(_) => return,
+        };
+
+        self.update(cmd);
+    }
+
+    fn set_content(&mut self, content: RenderableContent) {
+        self.renderable_content = content;
+        self.update_visible_search_matches();
+        #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+        self.sync_image_handles();
+        self.is_dirty = false;
+        self.clear_caches();
+    }
+
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    fn sync_image_handles(&mut self) {
+        let images = &self.renderable_content.images;
+        self.image_handles
+            .retain(|id, _| images.iter().any(|image| image.id == *id));
+        for image in images {
+            self.image_handles
+                .entry(image.id)
+                .or_insert_with(|| match &image.image.data {
+                    ImageData::Rgba(pixels) => image::Handle::from_pixels(
+                        image.image.width,
+                        image.image.height,
+                        pixels.clone(),
+                    ),
+                    #[cfg(feature = "kitty-graphics")]
+                    ImageData::Png(bytes) => {
+                        image::Handle::from_memory(bytes.clone())
+                    },
+                });
+        }
+    }
+
+    #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+    fn draw_images(
+        &self,
+        renderer: &mut iced::Renderer<Theme>,
+        bounds: Rectangle,
+    ) {
+        let display_offset = self.renderable_content.display_offset as i32;
+        iced::advanced::Renderer::with_layer(renderer, bounds, |renderer| {
+            for image in &self.renderable_content.images {
+                let Some(handle) = self.image_handles.get(&image.id) else {
+                    continue;
+                };
+
+                let image_bounds = Rectangle {
+                    x: bounds.x + image.column as f32 * self.font_measure.width,
+                    y: bounds.y
+                        + (image.line + display_offset) as f32
+                            * self.font_measure.height,
+                    width: image.image.width as f32,
+                    height: image.image.height as f32,
+                };
+                image::Renderer::draw(renderer, handle.clone(), image_bounds);
+            }
+        });
+    }
+
+    fn clear_caches(&self) {
+        self.line_caches.iter().for_each(Cache::clear);
+        self.cursor_cache.clear();
+    }
+
+    fn selection_point(&self, position: Point) -> (usize, usize, Side) {
+        let column = (position.x / self.font_measure.width).max(0.0);
+        let line = (position.y / self.font_measure.height).max(0.0);
+        let side = if column.fract() < 0.5 {
+            Side::Left
+        } else {
+            Side::Right
+        };
+
+        (line.floor() as usize, column.floor() as usize, side)
+    }
+
+    fn grid_cell(&self, position: Point) -> (usize, usize) {
+        let column = (position.x / self.font_measure.width).max(0.0);
+        let line = (position.y / self.font_measure.height).max(0.0);
+
+        (column.floor() as usize, line.floor() as usize)
+    }
+
+    fn clear_blinking_lines(&self) {
+        let display_offset = self.renderable_content.display_offset as i32;
+        for line in self
+            .renderable_content
+            .cells
+            .chunk_by(|a, b| a.line == b.line)
+            .filter(|line| line.iter().any(RenderableCell::is_blinking))
+        {
+            let row = (line[0].line + display_offset) as usize;
+            if let Some(cache) = self.line_caches.get(row) {
+                cache.clear();
+            }
+        }
+    }
+
+    fn cell_colors(&self, cell: &RenderableCell) -> (Color, Option<Color>) {
+        let mut fg = font::get_color(cell.fg, &self.active_theme.palette);
+        let mut bg = font::get_color(cell.bg, &self.active_theme.palette);
+        if cell.flags.contains(cell::Flags::DIM) {
+            if cell.flags.contains(cell::Flags::INVERSE) {
+                bg = font::dim_color(bg);
+            } else {
+                fg = font::dim_color(fg);
+            }
+        }
+
+        let highlight = self.search_highlight(cell);
+        if let Some(highlight) = highlight {
+            fg = self.active_theme.palette.background;
+            bg = highlight;
+        }
+
+        // The container already paints the default background, and filling
+        // it again would also stack the alpha of a translucent theme.
+        let is_default_bg = highlight.is_none()
+            && cell.bg == ansi::Color::Named(NamedColor::Background);
+        if is_default_bg {
+            return (fg, None);
+        }
+
+        (fg, Some(bg))
+    }
+
+    fn is_glyph_visible(&self, cell: &RenderableCell) -> bool {
+        let is_blinked_out = self.text_blinking
+            && !self.text_blink_visible
+            && cell.is_blinking();
+        cell.content != ' '
+            && cell.content != '\t'
+            && !is_blinked_out
+            && !cell.is_concealed()
+    }
+
+    fn cell_width(&self, cell: &RenderableCell) -> f32 {
+        if cell.flags.contains(cell::Flags::WIDE_CHAR) {
+            self.font_measure.width * 2.0
+        } else {
+            self.font_measure.width
+        }
+    }
+
+    fn draw_line_background(
+        &self,
+        frame: &mut Frame,
+        origin: Point,
+        line: &[RenderableCell],
+    ) {
+        let Some(first) = line.first() else {
+            return;
+        };
+        let y = origin.y
+            + (first.line + first.display_offset as i32) as f32
+                * self.font_measure.height;
+        let mut fill = |run: Option<(usize, usize, Color)>| {
+            if let Some((start, end, color)) = run {
+                let x = origin.x + start as f32 * self.font_measure.width;
+                let width = (end - start) as f32 * self.font_measure.width;
+                let size = Size::new(width, self.font_measure.height);
+                frame.fill(&Path::rectangle(Point { x, y }, size), color);
+            }
+        };
+
+        // Adjacent cells sharing a background are filled as a single run.
+        let mut run = None;
+        for cell in line {
+            if cell.flags.contains(cell::Flags::WIDE_CHAR_SPACER) {
+                continue;
+            }
+
+            let end = if cell.flags.contains(cell::Flags::WIDE_CHAR) {
+                cell.column + 2
+            } else {
+                cell.column + 1
+            };
+            run = match (run, self.cell_colors(cell).1) {
+                (Some((start, run_end, color)), Some(bg))
+                    if color == bg && run_end == cell.column =>
+                {
+                    Some((start, end, color))
+                },
+                (_, bg) => {
+                    fill(run);
+                    bg.map(|bg| (cell.column, end, bg))
+                },
+            };
+        }
+        fill(run);
+    }
+
+    fn draw_cell(
+        &self,
+        frame: &mut Frame,
+        origin: Point,
+        cell: &RenderableCell,
+    ) {
+        if cell.flags.contains(cell::Flags::WIDE_CHAR_SPACER) {
+            return;
+        }
+
+        let cell_height = self.font_measure.height;
+        let x = origin.x + cell.column as f32 * self.font_measure.width;
+        let y = origin.y
+            + (cell.line + cell.display_offset as i32) as f32 * cell_height;
+        let (fg, _) = self.cell_colors(cell);
+        let size = Size::new(self.cell_width(cell), cell_height);
+
+        if self.is_glyph_visible(cell)
+            && !self.draw_box_char(
+                frame,
+                cell.content,
+                Point::new(x, y),
+                size,
+                fg,
+            )
+        {
+            let text = Text {
+                content: cell.content.to_string(),
+                position: Point {
+                    x: x + size.width / 2.0,
+                    y: y + size.height / 2.0,
+                },
+                font: self.glyph_font(cell.content, cell.flags),
+                size: self.font_size,
+                color: fg,
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: Vertical::Center,
+                shaping: font::glyph_shaping(cell.content),
+                ..Text::default()
+            };
+
+            frame.fill_text(text);
+        }
+
+        let left = x;
+        let bottom = y + size.height;
+        let underline_color = cell.underline_color.map_or(fg, |color| {
+            font::get_color(color, &self.active_theme.palette)
+        });
+        let underline_y = bottom - LINE_THICKNESS * 2.0;
+        if cell.flags.contains(cell::Flags::DOUBLE_UNDERLINE) {
+            let thickness = LINE_THICKNESS * 0.75;
+            for offset in [thickness, thickness * 4.0] {
+                frame.fill_rectangle(
+                    Point {
+                        x: left,
+                        y: bottom - offset,
+                    },
+                    Size::new(size.width, thickness),
+                    underline_color,
+                );
+            }
+        } else if cell.flags.contains(cell::Flags::UNDERCURL) {
+            // One period per cell, so the wave continues across cells.
+            let amplitude = (size.height * 0.06).max(1.0);
+            let base = bottom - amplitude - LINE_THICKNESS;
+            let half = size.width / 2.0;
+            let curl = Path::new(|path| {
+                path.move_to(Point::new(left, base));
+                path.quadratic_curve_to(
+                    Point::new(left + half / 2.0, base - amplitude * 2.0),
+                    Point::new(left + half, base),
+                );
+                path.quadratic_curve_to(
+                    Point::new(left + half * 1.5, base + amplitude * 2.0),
+                    Point::new(left + size.width, base),
+                );
+            });
+            frame.stroke(
+                &curl,
+                Stroke::default()
+                    .with_width(LINE_THICKNESS)
+                    .with_color(underline_color),
+            );
+        } else if cell.flags.contains(cell::Flags::DOTTED_UNDERLINE) {
+            let mut dot = left;
+            while dot < left + size.width {
+                frame.fill_rectangle(
+                    Point::new(dot, underline_y),
+                    Size::new(LINE_THICKNESS, LINE_THICKNESS),
+                    underline_color,
+                );
+                dot += LINE_THICKNESS * 2.0;
+            }
+        } else if cell.flags.contains(cell::Flags::DASHED_UNDERLINE) {
+            frame.fill_rectangle(
+                Point::new(left + size.width / 4.0, underline_y),
+                Size::new(size.width / 2.0, LINE_THICKNESS),
+                underline_color,
+            );
+        } else if cell.flags.contains(cell::Flags::UNDERLINE)
+            || cell.hyperlink.is_some()
+        {
+            frame.fill_rectangle(
+                Point {
+                    x: left,
+                    y: underline_y,
+                },
+                Size::new(size.width, LINE_THICKNESS),
+                underline_color,
+            );
+        }
+
+        if cell.flags.contains(cell::Flags::STRIKEOUT) {
+            frame.fill_rectangle(
+                Point {
+                    x: left,
+                    y: bottom - (size.height + LINE_THICKNESS) / 2.0,
+                },
+                Size::new(size.width, LINE_THICKNESS),
+                fg,
+            );
+        }
+    }
+
+    fn cell_at(&self, position: Point) -> Option<&RenderableCell> {
+        let (column, line) = self.grid_cell(position);
+        let display_offset = self.renderable_content.display_offset as i32;
+        self.renderable_content.cells.iter().find(|cell| {
+            cell.column == column && cell.line + display_offset == line as i32
+        })
+    }
+
+    fn search_highlight(&self, cell: &RenderableCell) -> Option<Color> {
+        // Matches don't overlap, so only the first one ending at or after
+        // the cell can contain it.
+        let point = (cell.line, cell.column);
+        let first =
+            self.visible_search_matches.partition_point(|(_, range)| {
+                (range.end_line, range.end_column) < point
+            });
+        let (index, range) = self.visible_search_matches.get(first)?;
+        if !range.contains(cell.line, cell.column) {
+            return None;
+        }
+        let index = *index;
+        let palette = &self.active_theme.palette;
+        if Some(index) == self.current_search_match {
+            Some(palette.current_search_match)
+        } else {
+            Some(palette.search_match)
+        }
+    }
+
+    fn link_at(&self, position: Point) -> Option<&RenderableLink> {
+        let (column, line) = self.grid_cell(position);
+        let line = line as i32 - self.renderable_content.display_offset as i32;
+        self.renderable_content
+            .links
+            .iter()
+            .find(|link| link.range.contains(line, column))
+    }
+
+    fn is_link_at(&self, position: Point) -> bool {
+        self.cell_at(position)
+            .is_some_and(|cell| cell.hyperlink.is_some())
+            || self.link_at(position).is_some()
+    }
+
+    fn hyperlink_at(&self, position: Point) -> Option<String> {
+        self.cell_at(position)
+            .and_then(|cell| cell.hyperlink.clone())
+            .or_else(|| self.link_at(position).map(|link| link.uri.clone()))
+    }
+
+    fn draw_link_underline(
+        &self,
+        frame: &mut Frame,
+        origin: Point,
+        link: &RenderableLink,
+    ) {
+        let (columns, _) = self.grid_size();
+        let display_offset = self.renderable_content.display_offset as i32;
+        let color = self.active_theme.palette.foreground;
+        let range = link.range;
+        for line in range.start_line..=range.end_line {
+            let start_column = if line == range.start_line {
+                range.start_column
+            } else {
+                0
+            };
+            let end_column = if line == range.end_line {
+                range.end_column
+            } else {
+                usize::from(columns).saturating_sub(1)
+            };
+            let y = origin.y
+                + (line + display_offset + 1) as f32 * self.font_measure.height;
+            frame.fill_rectangle(
+                Point {
+                    x: origin.x + start_column as f32 * self.font_measure.width,
+                    y: y - LINE_THICKNESS * 2.0,
+                },
+                Size::new(
+                    (end_column + 1 - start_column) as f32
+                        * self.font_measure.width,
+                    LINE_THICKNESS,
+                ),
+                color,
+            );
+        }
+    }
+
+    fn cell_font(&self, flags: cell::Flags) -> Font {
+        let bold = flags.contains(cell::Flags::BOLD);
+        let italic = flags.contains(cell::Flags::ITALIC);
+        match (bold, italic) {
+            (true, true) => self.bold_italic_font,
+            (false, true) => self.italic_font,
+            (true, false) => self.bold_font,
+            (false, false) => self.font_type,
+        }
+    }
+
+    fn draw_box_char(
+        &self,
+        frame: &mut Frame,
+        c: char,
+        position: Point,
+        size: Size<f32>,
+        color: Color,
+    ) -> bool {
+        self.box_drawing
+            && box_drawing::draw(
+                frame,
+                c,
+                Rectangle::new(position, size),
+                color,
+            )
+    }
+
+    fn glyph_font(&self, c: char, flags: cell::Flags) -> Font {
+        font::glyph_font(c, self.cell_font(flags), &self.fallback_fonts)
+    }
+
+    fn on_input_written(&mut self, result: backend::Result<()>) {
+        if result.is_err() {
+            return;
+        }
+
+        if self.renderable_content.display_offset != 0 {
+            let content = self.backend.cells();
+            self.set_content(content);
+        }
+
+        self.reset_cursor_blink();
+    }
+
+    fn reset_cursor_blink(&mut self) {
+        if !self.cursor_blink_visible {
+            self.cursor_blink_visible = true;
+            self.cursor_cache.clear();
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Event> {
         container(self)
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(self.padding)
-            .style(iced::theme::Container::Custom(Box::new(Style)))
+            .style(iced::theme::Container::Custom(Box::new(Style {
+                background: self.active_theme.background(),
+            })))
             .into()
     }
 
-    fn handle_mouse_event(&self, event: iced::mouse::Event) -> Event {
+    fn handle_mouse_event(
+        &self,
+        state: &mut TermState,
+        bounds: Rectangle,
+        cursor: Cursor,
+        event: iced::mouse::Event,
+    ) -> Event {
+        let mode = self.backend.mode();
+        if mode.intersects(TermMode::MOUSE_MODE)
+            && !state.keyboard_modifiers.shift()
+        {
+            return self.report_mouse_event(state, bounds, cursor, event, mode);
+        }
+
         match event {
+            iced::mouse::Event::ButtonPressed(Button::Left) => {
+                match cursor.position_in(bounds) {
+                    Some(position) => {
+                        if state.keyboard_modifiers.control() {
+                            if let Some(uri) = self.hyperlink_at(position) {
+                                return Event::LinkClicked(self.id, uri);
+                            }
+                        }
+
+                        let click = Click::new(position, state.last_click);
+                        let selection_type = match click.kind() {
+                            click::Kind::Single => SelectionType::Simple,
+                            click::Kind::Double => SelectionType::Semantic,
+                            click::Kind::Triple => SelectionType::Lines,
+                        };
+                        state.last_click = Some(click);
+                        state.is_selecting = true;
+                        Event::SelectionStarted(
+                            self.id,
+                            position,
+                            selection_type,
+                        )
+                    },
+                    None => Event::Ignored(self.id),
+                }
+            },
+            iced::mouse::Event::ButtonPressed(Button::Right) => {
+                match cursor.position_in(bounds) {
+                    Some(position) => {
+                        let (column, line) = self.grid_cell(position);
+                        Event::ContextMenuRequested(
+                            self.id,
+                            ContextMenu {
+                                position,
+                                column,
+                                line,
+                                has_selection: self
+                                    .backend
+                                    .selection_range()
+                                    .is_some(),
+                            },
+                        )
+                    },
+                    None => Event::Ignored(self.id),
+                }
+            },
+            iced::mouse::Event::CursorMoved { .. } if state.is_selecting => {
+                match cursor.position_from(bounds.position()) {
+                    Some(position) => {
+                        Event::SelectionUpdated(self.id, position)
+                    },
+                    None => Event::Ignored(self.id),
+                }
+            },
+            iced::mouse::Event::ButtonReleased(Button::Left) => {
+                state.is_selecting = false;
+                Event::Ignored(self.id)
+            },
             iced::mouse::Event::WheelScrolled {
                 delta: ScrollDelta::Lines { x: _, y },
-            } => Event::ContainerScrolled(self.id, y),
+            } => self.scroll_lines(state, y),
+            iced::mouse::Event::WheelScrolled {
+                delta: ScrollDelta::Pixels { x: _, y },
+            } => self.scroll_lines(state, y / self.font_measure.height),
             _ => Event::Ignored(self.id),
         }
     }
 
-    fn handle_keyboard_event(&self, event: iced::keyboard::Event) -> Event {
+    // Trackpads report a few pixels or a fraction of a line at a time, so
+    // the remainder is kept until it adds up to a whole line.
+    fn scroll_lines(&self, state: &mut TermState, lines: f32) -> Event {
+        state.scroll_remainder += lines;
+        let whole = state.scroll_remainder.trunc();
+        state.scroll_remainder -= whole;
+        if whole == 0.0 {
+            return Event::Ignored(self.id);
+        }
+
+        Event::ContainerScrolled(self.id, whole)
+    }
+
+    fn report_mouse_event(
+        &self,
+        state: &mut TermState,
+        bounds: Rectangle,
+        cursor: Cursor,
+        event: iced::mouse::Event,
+        mode: TermMode,
+    ) -> Event {
+        let cell = match cursor.position_in(bounds) {
+            Some(position) => self.grid_cell(position),
+            None => match (event, state.last_reported_cell) {
+                (iced::mouse::Event::ButtonReleased(_), Some(cell)) => cell,
+                _ => return Event::Ignored(self.id),
+            },
+        };
+
+        let (code, pressed) = match event {
+            iced::mouse::Event::ButtonPressed(button) => {
+                match mouse::button_code(button) {
+                    Some(code) => {
+                        state.pressed_button = Some(code);
+                        (code, true)
+                    },
+                    None => return Event::Ignored(self.id),
+                }
+            },
+            iced::mouse::Event::ButtonReleased(button) => {
+                match mouse::button_code(button) {
+                    Some(code) => {
+                        state.pressed_button = None;
+                        (code, false)
+                    },
+                    None => return Event::Ignored(self.id),
+                }
+            },
+            iced::mouse::Event::CursorMoved { .. }
+                if state.last_reported_cell != Some(cell) =>
+            {
+                match mouse::motion_code(state.pressed_button, mode) {
+                    Some(code) => (code, true),
+                    None => return Event::Ignored(self.id),
+                }
+            },
+            iced::mouse::Event::WheelScrolled {
+                delta: ScrollDelta::Lines { y, .. },
+            } if y != 0.0 => (mouse::wheel_code(y), true),
+            iced::mouse::Event::WheelScrolled {
+                delta: ScrollDelta::Pixels { y, .. },
+            } if y != 0.0 => (mouse::wheel_code(y), true),
+            _ => return Event::Ignored(self.id),
+        };
+
+        state.last_reported_cell = Some(cell);
+        match mouse::report(
+            code,
+            pressed,
+            cell.0,
+            cell.1,
+            state.keyboard_modifiers,
+            mode,
+        ) {
+            Some(bytes) => Event::MouseReported(self.id, bytes),
+            None => Event::Ignored(self.id),
+        }
+    }
+
+    fn zoomed_font_size(
+        &self,
+        key_code: KeyCode,
+        modifiers: Modifiers,
+    ) -> Option<f32> {
+        let zoom = self.keyboard.zoom_modifiers?;
+        let size = match key_code {
+            KeyCode::Plus | KeyCode::Equals | KeyCode::NumpadAdd
+                if modifiers == zoom
+                    || modifiers == zoom | Modifiers::SHIFT =>
+            {
+                self.font_size + FONT_SIZE_STEP
+            },
+            KeyCode::Minus | KeyCode::NumpadSubtract if modifiers == zoom => {
+                self.font_size - FONT_SIZE_STEP
+            },
+            KeyCode::Key0 | KeyCode::Numpad0 if modifiers == zoom => {
+                self.default_font_size
+            },
+            _ => return None,
+        };
+
+        Some(font::clamp_font_size(size))
+    }
+
+    fn handle_keyboard_event(
+        &self,
+        state: &mut TermState,
+        clipboard: &mut dyn Clipboard,
+        event: iced::keyboard::Event,
+    ) -> Event {
+        if let iced::keyboard::Event::KeyPressed {
+            key_code,
+            modifiers,
+        } = event
+        {
+            if let Some(size) = self.zoomed_font_size(key_code, modifiers) {
+                return Event::FontSizeChanged(self.id, size);
+            }
+        }
+
         match event {
+            iced::keyboard::Event::KeyPressed {
+                key_code: KeyCode::V,
+                modifiers,
+            } if modifiers.control() && modifiers.shift() => {
+                match clipboard.read() {
+                    Some(text) => Event::TextPasted(self.id, text),
+                    None => Event::Ignored(self.id),
+                }
+            },
+            iced::keyboard::Event::KeyPressed {
+                key_code: KeyCode::C,
+                modifiers,
+            } if modifiers.control() && modifiers.shift() => {
+                match self.selection_text() {
+                    Some(text) => {
+                        clipboard.write(text.clone());
+                        Event::TextCopied(self.id, text)
+                    },
+                    None => Event::Ignored(self.id),
+                }
+            },
+            iced::keyboard::Event::KeyPressed {
+                key_code: key_code @ (KeyCode::Up | KeyCode::Down),
+                modifiers,
+            } if modifiers == Modifiers::CTRL | Modifiers::SHIFT
+                && !self.backend.is_alt_screen() =>
+            {
+                if key_code == KeyCode::Up {
+                    Event::PreviousPromptRequested(self.id)
+                } else {
+                    Event::NextPromptRequested(self.id)
+                }
+            },
+            iced::keyboard::Event::KeyPressed {
+                key_code: key_code @ (KeyCode::PageUp | KeyCode::PageDown),
+                modifiers,
+            } if self.keyboard.page_scroll_modifiers == Some(modifiers)
+                && !self.backend.is_alt_screen() =>
+            {
+                let (_, rows) = self.backend.size();
+                let page = f32::from(rows.saturating_sub(1).max(1));
+                let delta = if key_code == KeyCode::PageUp {
+                    page
+                } else {
+                    -page
+                };
+                Event::ContainerScrolled(self.id, delta)
+            },
+            iced::keyboard::Event::CharacterReceived(_)
+                if state.suppress_character =>
+            {
+                state.suppress_character = false;
+                Event::Ignored(self.id)
+            },
+            iced::keyboard::Event::CharacterReceived(c) if c.is_control() => {
+                Event::Ignored(self.id)
+            },
+            iced::keyboard::Event::CharacterReceived(c)
+                if state.keyboard_modifiers.alt() =>
+            {
+                let bytes =
+                    keyboard::char_to_bytes(c, state.keyboard_modifiers);
+                Event::KeyInputReceived(self.id, bytes)
+            },
             iced::keyboard::Event::CharacterReceived(c) => {
                 Event::InputReceived(self.id, c)
             },
+            iced::keyboard::Event::KeyPressed {
+                key_code,
+                modifiers,
+            } => {
+                let mode = self.backend.mode();
+                match keyboard::key_to_bytes(
+                    key_code,
+                    modifiers,
+                    mode,
+                    &self.keyboard,
+                ) {
+                    Some(bytes) => {
+                        state.suppress_character =
+                            keyboard::is_app_keypad_key(key_code, mode);
+                        Event::KeyInputReceived(self.id, bytes)
+                    },
+                    None => Event::Ignored(self.id),
+                }
+            },
             _ => Event::Ignored(self.id),
         }
     }
 }
 
-#[derive(Default)]
-struct Style;
+struct TermState {
+    keyboard_modifiers: Modifiers,
+    is_selecting: bool,
+    last_click: Option<Click>,
+    pressed_button: Option<u8>,
+    last_reported_cell: Option<(usize, usize)>,
+    suppress_character: bool,
+    is_window_focused: bool,
+    scroll_remainder: f32,
+    last_resize: Option<Instant>,
+}
+
+impl Default for TermState {
+    fn default() -> Self {
+        Self {
+            keyboard_modifiers: Modifiers::default(),
+            is_selecting: false,
+            last_click: None,
+            pressed_button: None,
+            last_reported_cell: None,
+            suppress_character: false,
+            is_window_focused: true,
+            scroll_remainder: 0.0,
+            last_resize: None,
+        }
+    }
+}
+
+impl TermState {
+    // Lets a resize through if none was in the last `RESIZE_INTERVAL_MS`,
+    // and otherwise returns when the next one is due.
+    fn throttle_resize(&mut self, now: Instant) -> Option<Instant> {
+        let interval = Duration::from_millis(RESIZE_INTERVAL_MS);
+        match self.last_resize {
+            Some(last) if now < last + interval => Some(last + interval),
+            _ => {
+                self.last_resize = Some(now);
+                None
+            },
+        }
+    }
+}
+
+struct Style {
+    background: Color,
+}
 
 impl container::StyleSheet for Style {
     type Style = Theme;
 
     fn appearance(&self, _style: &Self::Style) -> container::Appearance {
         container::Appearance {
-            background: Some(Color::from_rgb8(40, 39, 39).into()),
+            background: Some(self.background.into()),
             ..container::Appearance::default()
         }
     }
@@ -178,6 +1628,14 @@ impl Widget<Event, iced::Renderer<Theme>> for &Term {
         Length::Fill
     }
 
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<TermState>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(TermState::default())
+    }
+
     fn layout(
         &self,
         _renderer: &iced::Renderer,
@@ -202,8 +1660,39 @@ impl Widget<Event, iced::Renderer<Theme>> for &Term {
         _shell: &mut iced_graphics::core::Shell<'_, Event>,
         _viewport: &Rectangle,
     ) -> iced::event::Status {
-        if self.size != _layout.bounds().size() {
-            _shell.publish(Event::Resized(self.id(), _layout.bounds().size()));
+        let state = _state.state.downcast_mut::<TermState>();
+        // A drag publishes at most one resize per interval. The redraw
+        // requested for a throttled one publishes the size the drag ended at.
+        let size = _layout.bounds().size();
+        if self.auto_fit && self.size != size {
+            match state.throttle_resize(Instant::now()) {
+                Some(due) => {
+                    _shell.request_redraw(window::RedrawRequest::At(due))
+                },
+                None => _shell.publish(Event::Resized(self.id(), size)),
+            }
+        }
+        if let iced::Event::Keyboard(iced::keyboard::Event::ModifiersChanged(
+            modifiers,
+        )) = event
+        {
+            state.keyboard_modifiers = modifiers;
+        }
+
+        match event {
+            iced::Event::Window(window::Event::Focused) => {
+                state.is_window_focused = true;
+                self.cursor_cache.clear();
+            },
+            iced::Event::Window(window::Event::Unfocused) => {
+                // Modifier releases are missed while another window has
+                // the keyboard.
+                state.is_window_focused = false;
+                state.keyboard_modifiers = Modifiers::default();
+                state.suppress_character = false;
+                self.cursor_cache.clear();
+            },
+            _ => {},
         }
 
         if !self.is_focused {
@@ -211,11 +1700,16 @@ impl Widget<Event, iced::Renderer<Theme>> for &Term {
         }
 
         let term_event = match event {
-            iced::Event::Mouse(mouse_event) => {
-                self.handle_mouse_event(mouse_event)
-            },
-            iced::Event::Keyboard(keyboard_event) => {
-                self.handle_keyboard_event(keyboard_event)
+            iced::Event::Mouse(mouse_event) => self.handle_mouse_event(
+                state,
+                _layout.bounds(),
+                _cursor,
+                mouse_event,
+            ),
+            iced::Event::Keyboard(keyboard_event)
+                if state.is_window_focused =>
+            {
+                self.handle_keyboard_event(state, _clipboard, keyboard_event)
             },
             _ => Event::Ignored(self.id),
         };
@@ -229,6 +1723,21 @@ impl Widget<Event, iced::Renderer<Theme>> for &Term {
         }
     }
 
+    fn mouse_interaction(
+        &self,
+        _state: &Tree,
+        layout: iced::advanced::Layout<'_>,
+        cursor: Cursor,
+        _viewport: &Rectangle,
+        _renderer: &iced::Renderer<Theme>,
+    ) -> Interaction {
+        match cursor.position_in(layout.bounds()) {
+            Some(position) if self.is_link_at(position) => Interaction::Pointer,
+            Some(_) => Interaction::Text,
+            None => Interaction::Idle,
+        }
+    }
+
     fn draw(
         &self,
         _state: &iced::advanced::widget::Tree,
@@ -239,52 +1748,154 @@ impl Widget<Event, iced::Renderer<Theme>> for &Term {
         _cursor: Cursor,
         viewport: &Rectangle,
     ) {
-        let geom = self.cache.draw(renderer, viewport.size(), |frame| {
-            for cell in &self.renderable_content {
-                let cell_width = self.font_measure.width as f64;
-                let cell_height = self.font_measure.height as f64;
-
-                let x = cell.column as f64 * cell_width;
-                let y = (cell.line as f64 + cell.display_offset as f64)
-                    * cell_height;
-                let fg = font::get_color(cell.fg);
-                let bg = font::get_color(cell.bg);
-
-                let size = Size::new(cell_width as f32, cell_height as f32);
-                let background = Path::rectangle(
-                    Point {
-                        x: x as f32 + _layout.position().x,
-                        y: y as f32 + _layout.position().y,
+        if self.auto_fit && self.size == Size::ZERO {
+            return;
+        }
+
+        let display_offset = self.renderable_content.display_offset as i32;
+        let origin = _layout.position();
+        let mut geometries = Vec::new();
+        for line in self
+            .renderable_content
+            .cells
+            .chunk_by(|a, b| a.line == b.line)
+        {
+            let row = (line[0].line + display_offset) as usize;
+            if let Some(cache) = self.line_caches.get(row) {
+                let geom = cache.draw(renderer, viewport.size(), |frame| {
+                    self.draw_line_background(frame, origin, line);
+                    for cell in line {
+                        self.draw_cell(frame, origin, cell);
+                    }
+                });
+                geometries.push(geom);
+            }
+        }
+
+        let state = _state.state.downcast_ref::<TermState>();
+        let is_focused = self.is_focused && state.is_window_focused;
+        let cursor_geom =
+            self.cursor_cache.draw(renderer, viewport.size(), |frame| {
+                let cursor = &self.renderable_content.cursor;
+                if !self.show_cursor
+                    || (is_focused && !self.cursor_blink_visible)
+                    || cursor.shape == CursorShape::Hidden
+                {
+                    return;
+                }
+
+                let cell_width =
+                    if cursor.flags.contains(cell::Flags::WIDE_CHAR) {
+                        self.font_measure.width * 2.0
+                    } else {
+                        self.font_measure.width
+                    };
+                let cell_height = self.font_measure.height;
+                let x = _layout.position().x
+                    + cursor.column as f32 * self.font_measure.width;
+                let y = _layout.position().y
+                    + (cursor.line as f32
+                        + self.renderable_content.display_offset as f32)
+                        * cell_height;
+                let palette = &self.active_theme.palette;
+                let fg = palette
+                    .cursor
+                    .unwrap_or_else(|| font::get_color(cursor.fg, palette));
+                let bg = font::get_color(cursor.bg, palette);
+                // A fixed cursor color can be close to the cell background
+                // the glyph would otherwise be drawn in.
+                let bg = match palette.cursor {
+                    Some(cursor_color) => {
+                        font::contrasting_color(cursor_color, bg)
                     },
-                    size,
-                );
-                frame.fill(&background, bg);
+                    None => bg,
+                };
 
-                if cell.content != ' ' && cell.content != '\t' {
+                if !is_focused {
+                    let outline = Path::rectangle(
+                        Point::new(
+                            x + LINE_THICKNESS / 2.0,
+                            y + LINE_THICKNESS / 2.0,
+                        ),
+                        Size::new(
+                            cell_width - LINE_THICKNESS,
+                            cell_height - LINE_THICKNESS,
+                        ),
+                    );
+                    frame.stroke(
+                        &outline,
+                        Stroke::default()
+                            .with_width(LINE_THICKNESS)
+                            .with_color(fg),
+                    );
+                    return;
+                }
+
+                let (position, size) = match cursor.shape {
+                    CursorShape::Block | CursorShape::Hidden => {
+                        (Point { x, y }, Size::new(cell_width, cell_height))
+                    },
+                    CursorShape::Underline => {
+                        let height = (cell_height * 0.15).max(1.0);
+                        (
+                            Point {
+                                x,
+                                y: y + cell_height - height,
+                            },
+                            Size::new(cell_width, height),
+                        )
+                    },
+                    CursorShape::Bar => (
+                        Point { x, y },
+                        Size::new((cell_width * 0.1).max(1.0), cell_height),
+                    ),
+                };
+                frame.fill(&Path::rectangle(position, size), fg);
+
+                if cursor.shape == CursorShape::Block
+                    && cursor.content != ' '
+                    && cursor.content != '\t'
+                    && !self.draw_box_char(
+                        frame,
+                        cursor.content,
+                        Point { x, y },
+                        Size::new(cell_width, cell_height),
+                        bg,
+                    )
+                {
                     let text = Text {
-                        content: cell.content.to_string(),
+                        content: cursor.content.to_string(),
                         position: Point {
-                            x: _layout.position().x
-                                + x as f32
-                                + size.width / 2.0,
-                            y: _layout.position().y
-                                + y as f32
-                                + size.height / 2.0,
+                            x: x + cell_width / 2.0,
+                            y: y + cell_height / 2.0,
                         },
-                        font: Font::default(),
+                        font: self.glyph_font(cursor.content, cursor.flags),
                         size: self.font_size,
-                        color: fg,
+                        color: bg,
                         horizontal_alignment: Horizontal::Center,
                         vertical_alignment: Vertical::Center,
+                        shaping: font::glyph_shaping(cursor.content),
                         ..Text::default()
                     };
 
                     frame.fill_text(text);
                 }
-            }
-        });
+            });
+
+        geometries.push(cursor_geom);
 
-        renderer.draw(vec![geom]);
+        let hovered_link = _cursor
+            .position_in(_layout.bounds())
+            .and_then(|position| self.link_at(position));
+        if let Some(link) = hovered_link {
+            let mut frame = Frame::new(renderer, viewport.size());
+            self.draw_link_underline(&mut frame, origin, link);
+            geometries.push(frame.into_geometry());
+        }
+
+        renderer.draw(geometries);
+        #[cfg(any(feature = "sixel", feature = "kitty-graphics"))]
+        self.draw_images(renderer, _layout.bounds());
     }
 }
 
@@ -293,3 +1904,286 @@ impl<'a> From<&'a Term> for Element<'a, Event, iced::Renderer<Theme>> {
         Self::new(widget)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::widget::canvas::Geometry;
+    use iced_graphics::damage::Damage;
+    use iced_tiny_skia::Primitive;
+
+    fn headless(id: u64) -> Term {
+        let settings = TermSettings::default();
+        let backend = Pty::new_headless(id, settings.backend.clone());
+        Term::with_backend(id, settings, backend)
+    }
+
+    fn screen_text(term: &Term) -> String {
+        term.backend.grid_text().join("\n")
+    }
+
+    fn drawn_primitives(term: &mut Term) -> Vec<Primitive> {
+        let content = term.backend.cells();
+        term.set_content(content);
+        let backend = iced_tiny_skia::Backend::new(Default::default());
+        let renderer: iced::Renderer<Theme> =
+            iced::Renderer::TinySkia(iced_tiny_skia::Renderer::new(backend));
+        let mut frame = Frame::new(&renderer, Size::new(800.0, 600.0));
+        for cell in &term.renderable_content.cells {
+            term.draw_cell(&mut frame, Point::ORIGIN, cell);
+        }
+
+        let mut primitives = Vec::new();
+        let Geometry::TinySkia(primitive) = frame.into_geometry() else {
+            unreachable!("frame created for tiny-skia")
+        };
+        flatten(primitive, &mut primitives);
+        primitives
+    }
+
+    fn flatten(primitive: Primitive, primitives: &mut Vec<Primitive>) {
+        match primitive {
+            Primitive::Clip { content, .. }
+            | Primitive::Translate { content, .. } => {
+                flatten(*content, primitives)
+            },
+            Primitive::Group { primitives: group } => {
+                group.into_iter().for_each(|p| flatten(p, primitives))
+            },
+            primitive => primitives.push(primitive),
+        }
+    }
+
+    fn shape_bounds(primitives: &[Primitive]) -> Vec<Rectangle> {
+        primitives
+            .iter()
+            .filter(|primitive| matches!(primitive, Primitive::Custom(_)))
+            .map(Damage::bounds)
+            .collect()
+    }
+
+    #[test]
+    fn events_route_to_matching_terminal() {
+        let mut first = headless(1);
+        let mut second = headless(2);
+        let events = [
+            Event::DataUpdated(1, b"first".to_vec()),
+            Event::DataUpdated(2, b"second".to_vec()),
+        ];
+
+        for event in events {
+            first.handle_event(event.clone());
+            second.handle_event(event);
+        }
+
+        assert!(screen_text(&first).contains("first"));
+        assert!(!screen_text(&first).contains("second"));
+        assert!(screen_text(&second).contains("second"));
+        assert!(!screen_text(&second).contains("first"));
+    }
+
+    #[test]
+    fn strikeout_crosses_middle_of_cell() {
+        let mut term = headless(0);
+        term.backend.update(b"\r\n\x1b[9mX".to_vec());
+        let primitives = drawn_primitives(&mut term);
+        let rects = shape_bounds(&primitives);
+
+        let height = term.font_measure.height;
+        assert_eq!(rects.len(), 1);
+        let middle = rects[0].y + rects[0].height / 2.0;
+        assert!(
+            (middle - height * 1.5).abs() < 0.5,
+            "strikeout at {}",
+            middle
+        );
+    }
+
+    #[test]
+    fn wide_char_spacer_is_not_drawn() {
+        let mut term = headless(0);
+        term.backend.update("中x".as_bytes().to_vec());
+        let primitives = drawn_primitives(&mut term);
+        let texts: Vec<&str> = primitives
+            .iter()
+            .filter_map(|primitive| match primitive {
+                Primitive::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        let spacer = term
+            .renderable_content
+            .cells
+            .iter()
+            .find(|cell| cell.line == 0 && cell.column == 1);
+        assert!(spacer
+            .unwrap()
+            .flags
+            .contains(cell::Flags::WIDE_CHAR_SPACER));
+        assert_eq!(texts, ["中", "x"]);
+    }
+
+    #[test]
+    fn concealed_glyphs_are_not_drawn() {
+        let mut term = headless(0);
+        term.backend.update(b"\x1b[8mSECRET\x1b[0m shown".to_vec());
+        let content = term.backend.cells();
+        let line: Vec<&RenderableCell> =
+            content.cells.iter().filter(|cell| cell.line == 0).collect();
+
+        for cell in &line[..6] {
+            assert!(cell.flags.contains(cell::Flags::HIDDEN));
+            assert!(!term.is_glyph_visible(cell));
+        }
+        for cell in &line[7..12] {
+            assert!(!cell.flags.contains(cell::Flags::HIDDEN));
+            assert!(term.is_glyph_visible(cell));
+        }
+    }
+
+    #[test]
+    fn selection_reveals_concealed_glyphs() {
+        let mut term = headless(0);
+        term.backend.update(b"\x1b[8mSECRET\x1b[0m".to_vec());
+        term.backend
+            .start_selection(SelectionType::Simple, 0, 0, Side::Left);
+        let content = term.backend.update_selection(0, 5, Side::Right);
+        let secret = content.cells.iter().find(|cell| cell.content == 'S');
+
+        assert!(term.is_glyph_visible(secret.unwrap()));
+    }
+
+    #[test]
+    fn ctrl_shift_minus_is_not_zoom() {
+        let term = headless(0);
+        let ctrl = Modifiers::CTRL;
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+
+        assert!(term.zoomed_font_size(KeyCode::Minus, ctrl).is_some());
+        assert!(term.zoomed_font_size(KeyCode::Equals, ctrl_shift).is_some());
+        assert_eq!(term.zoomed_font_size(KeyCode::Minus, ctrl_shift), None);
+        assert_eq!(
+            keyboard::key_to_bytes(
+                KeyCode::Minus,
+                ctrl_shift,
+                TermMode::empty(),
+                &KeyboardSettings::default(),
+            ),
+            Some(vec![0x1f])
+        );
+    }
+
+    #[test]
+    fn zoom_can_be_disabled() {
+        let mut term = headless(0);
+        term.keyboard.zoom_modifiers = None;
+
+        assert_eq!(
+            term.zoomed_font_size(KeyCode::Minus, Modifiers::CTRL),
+            None
+        );
+    }
+
+    #[test]
+    fn background_override_keeps_theme_opacity() {
+        let mut term = headless(0);
+        term.update(Command::ChangeTheme(Box::new(ThemeSettings {
+            background_opacity: 0.5,
+            ..ThemeSettings::default()
+        })));
+        let color = Color::from_rgb(1.0, 0.0, 0.0);
+        term.update(Command::ChangeColor(
+            DynamicColor::Background,
+            Some(color),
+        ));
+
+        assert_eq!(term.active_theme.background(), Color { a: 0.5, ..color });
+    }
+
+    #[test]
+    fn color_reply_follows_overrides() {
+        let mut term = headless(0);
+        let color = Color::from_rgb8(0x12, 0x34, 0x56);
+        term.update(Command::ChangeColor(
+            DynamicColor::Indexed(3),
+            Some(color),
+        ));
+
+        assert_eq!(term.dynamic_color(DynamicColor::Indexed(3)), color);
+        assert_eq!(
+            term.dynamic_color(DynamicColor::Indexed(16)),
+            Color::from_rgb8(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn resizes_are_throttled_during_a_drag() {
+        let mut state = TermState::default();
+        let start = Instant::now();
+        let interval = Duration::from_millis(RESIZE_INTERVAL_MS);
+
+        assert_eq!(state.throttle_resize(start), None);
+        assert_eq!(
+            state.throttle_resize(start + interval / 2),
+            Some(start + interval)
+        );
+        assert_eq!(state.throttle_resize(start + interval), None);
+    }
+
+    #[test]
+    fn horizontal_wheel_is_not_reported() {
+        let term = headless(0);
+        let mut state = TermState::default();
+        let bounds = Rectangle::new(Point::ORIGIN, Size::new(100.0, 100.0));
+        let cursor = Cursor::Available(Point::new(10.0, 10.0));
+        let report = |state: &mut TermState, x, y| {
+            term.report_mouse_event(
+                state,
+                bounds,
+                cursor,
+                iced::mouse::Event::WheelScrolled {
+                    delta: ScrollDelta::Lines { x, y },
+                },
+                TermMode::MOUSE_REPORT_CLICK | TermMode::SGR_MOUSE,
+            )
+        };
+
+        assert!(matches!(report(&mut state, 1.0, 0.0), Event::Ignored(0)));
+        assert!(matches!(
+            report(&mut state, 0.0, 1.0),
+            Event::MouseReported(0, _)
+        ));
+    }
+
+    #[test]
+    fn only_visible_search_matches_are_looked_up() {
+        let mut term = headless(0);
+        let rows = term.grid_size().1 as usize;
+        let output = "match\r\n".repeat(rows * 3);
+        term.update(Command::RenderData(output.into_bytes()));
+        term.update(Command::Redraw);
+        let matches = term.search("match", false);
+        let current = matches.len() - 1;
+        term.set_search_matches(matches, Some(current));
+
+        assert!(term.visible_search_matches.len() <= rows);
+        let cells = term.renderable_content.cells.clone();
+        let first = cells.iter().find(|cell| cell.content == 'm').unwrap();
+        let blank = RenderableCell {
+            column: 10,
+            ..first.clone()
+        };
+        assert_eq!(
+            term.search_highlight(first),
+            Some(term.active_theme.palette.search_match)
+        );
+        assert_eq!(term.search_highlight(&blank), None);
+
+        let last = cells.iter().rev().find(|cell| cell.content == 'h');
+        assert_eq!(
+            term.search_highlight(last.unwrap()),
+            Some(term.active_theme.palette.current_search_match)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use iced::Color;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct ColorPalette {
+    pub foreground: Color,
+    pub background: Color,
+    /// Fixed cursor color. When unset the cursor is drawn inverted, in the
+    /// color of the text under it.
+    pub cursor: Option<Color>,
+    pub search_match: Color,
+    pub current_search_match: Color,
+    pub black: Color,
+    pub red: Color,
+    pub green: Color,
+    pub yellow: Color,
+    pub blue: Color,
+    pub magenta: Color,
+    pub cyan: Color,
+    pub white: Color,
+    pub bright_black: Color,
+    pub bright_red: Color,
+    pub bright_green: Color,
+    pub bright_yellow: Color,
+    pub bright_blue: Color,
+    pub bright_magenta: Color,
+    pub bright_cyan: Color,
+    pub bright_white: Color,
+    /// Colors for indices 16 to 255, in place of the default color cube and
+    /// grayscale ramp.
+    pub indexed: HashMap<u8, Color>,
+}
+
+impl Default for ColorPalette {
+    fn default() -> Self {
+        Self {
+            foreground: Color::from_rgb8(235, 218, 177),
+            background: Color::from_rgb8(40, 39, 39),
+            cursor: None,
+            search_match: Color::from_rgb8(214, 152, 33),
+            current_search_match: Color::from_rgb8(254, 128, 25),
+            black: Color::from_rgb8(40, 39, 39),
+            red: Color::from_rgb8(203, 35, 29),
+            green: Color::from_rgb8(152, 150, 26),
+            yellow: Color::from_rgb8(214, 152, 33),
+            blue: Color::from_rgb8(69, 132, 135),
+            magenta: Color::from_rgb8(176, 97, 133),
+            cyan: Color::from_rgb8(104, 156, 105),
+            white: Color::from_rgb8(168, 152, 131),
+            bright_black: Color::from_rgb8(146, 130, 115),
+            bright_red: Color::from_rgb8(250, 72, 52),
+            bright_green: Color::from_rgb8(184, 186, 38),
+            bright_yellow: Color::from_rgb8(249, 188, 47),
+            bright_blue: Color::from_rgb8(131, 164, 151),
+            bright_magenta: Color::from_rgb8(210, 133, 154),
+            bright_cyan: Color::from_rgb8(142, 191, 123),
+            bright_white: Color::from_rgb8(235, 218, 177),
+            indexed: HashMap::new(),
+        }
+    }
+}
+
+impl ColorPalette {
+    pub(crate) fn set_indexed(&mut self, index: u8, color: Color) {
+        let named = match index {
+            0 => &mut self.black,
+            1 => &mut self.red,
+            2 => &mut self.green,
+            3 => &mut self.yellow,
+            4 => &mut self.blue,
+            5 => &mut self.magenta,
+            6 => &mut self.cyan,
+            7 => &mut self.white,
+            8 => &mut self.bright_black,
+            9 => &mut self.bright_red,
+            10 => &mut self.bright_green,
+            11 => &mut self.bright_yellow,
+            12 => &mut self.bright_blue,
+            13 => &mut self.bright_magenta,
+            14 => &mut self.bright_cyan,
+            15 => &mut self.bright_white,
+            _ => {
+                self.indexed.insert(index, color);
+                return;
+            },
+        };
+        *named = color;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ThemeSettings {
+    pub palette: ColorPalette,
+    pub background_opacity: f32,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            palette: ColorPalette::default(),
+            background_opacity: 1.0,
+        }
+    }
+}
+
+impl ThemeSettings {
+    pub fn background(&self) -> Color {
+        Color {
+            a: self.background_opacity.clamp(0.0, 1.0),
+            ..self.palette.background
+        }
+    }
+}